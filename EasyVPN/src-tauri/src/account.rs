@@ -15,12 +15,22 @@ use hostname;
 use rand;
 use machine_uid;
 use std::path::PathBuf;
+use secrecy::{Secret, ExposeSecret};
+use sha2::Sha256;
+use hkdf::Hkdf;
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use ed25519_dalek::{SigningKey, Signer};
 
 // 服务器域名常量
 pub const API_BASE_URL: &str = "http://localhost:8080";
 // 文件名常量 - 只保留文件名部分
 const ACCOUNT_FILENAME: &str = "account.json";
 const DEVICE_ID_FILENAME: &str = "deviceId.json";
+const DEVICE_KEY_FILENAME: &str = "deviceKey.json";
+// HKDF的info参数，用于和其它可能的派生密钥区分开
+const AT_REST_KEY_INFO: &[u8] = b"easyvpn-at-rest-v1";
 
 // 获取应用程序数据目录下的完整文件路径
 fn get_app_data_file(app_handle: &tauri::AppHandle, filename: &str) -> Result<PathBuf, AccountError> {
@@ -46,6 +56,11 @@ fn get_device_id_file_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, Acc
     get_app_data_file(app_handle, DEVICE_ID_FILENAME)
 }
 
+// 获取设备身份密钥文件路径
+fn get_device_key_file_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, AccountError> {
+    get_app_data_file(app_handle, DEVICE_KEY_FILENAME)
+}
+
 // 定义一个辅助函数，将null值转换为空字符串
 fn empty_string_as_none<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
@@ -55,13 +70,95 @@ where
     Ok(opt.unwrap_or_default())
 }
 
+// 定义一个辅助函数，将null值转换为空字符串并包装进Secret，避免令牌被意外序列化到日志里
+fn empty_secret_as_none<'de, D>(deserializer: D) -> Result<Secret<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let opt = Option::<String>::deserialize(deserializer)?;
+    Ok(Secret::new(opt.unwrap_or_default()))
+}
+
+// Secret<String>不实现Serialize（secrecy的设计就是强制显式opt-in），
+// 但account.json落盘、account-status-changed事件都需要带上token，只能在这里手动暴露
+fn serialize_secret<S>(secret: &Secret<String>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(secret.expose_secret())
+}
+
+// account.json/deviceId.json落盘时的加密信封：nonce和密文均为base64编码
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedEnvelope {
+    nonce: String,
+    ciphertext: String,
+}
+
+// 派生本地静态加密使用的256位密钥：对本机唯一ID做HKDF-SHA256
+// 密钥仅与当前机器绑定，换机器后旧的加密文件会解密失败，按未命中处理即可（重新登录/重新生成设备ID）
+fn derive_encryption_key() -> Result<[u8; 32], AccountError> {
+    let machine_id = machine_uid::get()
+        .map_err(|e| AccountError::Other(format!("获取机器ID失败: {:?}", e)))?;
+    let hk = Hkdf::<Sha256>::new(None, machine_id.as_bytes());
+    let mut key = [0u8; 32];
+    hk.expand(AT_REST_KEY_INFO, &mut key)
+        .map_err(|e| AccountError::Other(format!("派生加密密钥失败: {}", e)))?;
+    Ok(key)
+}
+
+// 用AES-256-GCM加密数据，返回可直接写入文件的JSON信封字符串
+fn encrypt_at_rest(plaintext: &[u8]) -> Result<String, AccountError> {
+    let key_bytes = derive_encryption_key()?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| AccountError::Other(format!("加密本地数据失败: {}", e)))?;
+
+    let envelope = EncryptedEnvelope {
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+    Ok(serde_json::to_string(&envelope)?)
+}
+
+// 解密encrypt_at_rest写入的信封。返回(明文, 是否为旧版明文文件)，
+// 旧版文件（升级前写入的明文JSON）不是合法信封格式，原样当作明文返回，由调用方负责迁移重新加密落盘
+fn decrypt_at_rest(data: &str) -> Result<(Vec<u8>, bool), AccountError> {
+    let envelope: EncryptedEnvelope = match serde_json::from_str(data) {
+        Ok(envelope) => envelope,
+        Err(_) => return Ok((data.as_bytes().to_vec(), true)),
+    };
+
+    let key_bytes = derive_encryption_key()?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes));
+    let nonce_bytes = BASE64
+        .decode(&envelope.nonce)
+        .map_err(|e| AccountError::Other(format!("解码nonce失败: {}", e)))?;
+    let ciphertext = BASE64
+        .decode(&envelope.ciphertext)
+        .map_err(|e| AccountError::Other(format!("解码密文失败: {}", e)))?;
+
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce_bytes), ciphertext.as_slice())
+        .map_err(|e| AccountError::Other(format!("解密本地数据失败: {}", e)))?;
+    Ok((plaintext, false))
+}
+
+// 加密明文后整体写入文件
+fn write_encrypted_file(path: &Path, plaintext: &[u8]) -> Result<(), AccountError> {
+    let envelope_json = encrypt_at_rest(plaintext)?;
+    fs::write(path, envelope_json).map_err(AccountError::from)
+}
+
 // 定义账号信息结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
-    #[serde(default, deserialize_with = "empty_string_as_none")]
-    pub accessToken: String,
-    #[serde(default, deserialize_with = "empty_string_as_none")]
-    pub refreshToken: String,
+    #[serde(default, deserialize_with = "empty_secret_as_none", serialize_with = "serialize_secret")]
+    pub accessToken: Secret<String>,
+    #[serde(default, deserialize_with = "empty_secret_as_none", serialize_with = "serialize_secret")]
+    pub refreshToken: Secret<String>,
     #[serde(default)]
     pub status: String,
     #[serde(default)]
@@ -84,8 +181,8 @@ pub struct Account {
 impl Default for Account {
     fn default() -> Self {
         Self {
-            accessToken: String::new(),
-            refreshToken: String::new(),
+            accessToken: Secret::new(String::new()),
+            refreshToken: Secret::new(String::new()),
             status: String::from("NO_INIT"),
             serviceExpiryDate: None,
             username: None,
@@ -174,57 +271,180 @@ impl From<serde_json::Error> for AccountError {
     }
 }
 
-// 静态内存缓存，用于存储设备ID
+// 静态内存缓存，用于存储设备ID。用tokio::sync::RwLock而不是std::sync::RwLock，
+// 是因为它在async函数中被访问（参见get_machine_id），等锁时让出执行权而不是阻塞异步运行时的工作线程；
+// 每个guard都在下一次.await之前释放
+lazy_static::lazy_static! {
+    static ref DEVICE_ID: Arc<tokio::sync::RwLock<Option<String>>> = Arc::new(tokio::sync::RwLock::new(None));
+}
+
+// 静态内存缓存，用于存储设备的Ed25519身份密钥对，避免每次签名都重新读盘解密
 lazy_static::lazy_static! {
-    static ref DEVICE_ID: Arc<RwLock<Option<String>>> = Arc::new(RwLock::new(None));
+    static ref DEVICE_KEYPAIR: Arc<RwLock<Option<SigningKey>>> = Arc::new(RwLock::new(None));
+}
+
+// 账号状态定时轮询的间隔
+const STATUS_POLL_INTERVAL_SECS: u64 = 60;
+// 保证整个应用生命周期内只启动一个定时轮询任务
+static POLLING_STARTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+// 账号状态刷新的触发来源，定时轮询和前端主动刷新共用同一条refresh_account路径
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchReason {
+    Poll,
+    Manual,
+}
+
+// 设备列表缓存的新鲜度阈值，阈值内直接复用缓存，不再触发一次设备登录
+const DEVICES_FRESHNESS_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(60);
+
+// 设备列表更新被拒绝时返回的错误：新收到的设备列表比已缓存的更旧，可能是一次乱序/重试的网络响应
+#[derive(Debug)]
+pub struct DeviceListError {
+    pub incoming_max_last_online_time: String,
+    pub cached_max_last_online_time: String,
+}
+
+impl fmt::Display for DeviceListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "设备列表已过期：收到的最新在线时间({})早于缓存中的最新在线时间({})",
+            self.incoming_max_last_online_time, self.cached_max_last_online_time
+        )
+    }
+}
+
+impl Error for DeviceListError {}
+
+// 设备列表缓存：记录上一次成功写入的设备列表及写入时刻
+struct DevicesCache {
+    devices: Vec<DeviceInfo>,
+    cached_at: std::time::Instant,
 }
 
 // 账号管理器结构体
 pub struct AccountManager {
-    account: Arc<RwLock<Account>>,
+    // 用tokio::sync::RwLock而不是std::sync::RwLock：account在async命令中被并发访问，
+    // 等锁时让出执行权而不是阻塞异步运行时的工作线程；读出token等数据后立即释放guard，再发起网络请求
+    account: Arc<tokio::sync::RwLock<Account>>,
     client: Client,
+    // 避免并发命令在access_token同时过期时各自发起一次刷新请求
+    refresh_lock: tokio::sync::Mutex<()>,
+    // 上一次刷新得到的账号状态，用于判断是否需要向前端广播account-status-changed事件
+    last_status: RwLock<Option<AccountStatus>>,
+    // 设备列表缓存，供get_current_device/list_devices在新鲜度阈值内复用
+    devices_cache: RwLock<Option<DevicesCache>>,
 }
 
 impl AccountManager {
     // 创建新的账号管理器实例
     pub fn new() -> Self {
         Self {
-            account: Arc::new(RwLock::new(Account::default())),
+            account: Arc::new(tokio::sync::RwLock::new(Account::default())),
             client: Client::new(),
+            refresh_lock: tokio::sync::Mutex::new(()),
+            last_status: RwLock::new(None),
+            devices_cache: RwLock::new(None),
         }
     }
 
     // 获取当前账号信息的克隆
-    pub fn get_account(&self) -> Result<Account, AccountError> {
-        match self.account.read() {
-            Ok(account) => Ok(account.clone()),
-            Err(_) => Ok(Account::default()) // 如果读取失败，返回默认值
-        }
+    pub async fn get_account(&self) -> Result<Account, AccountError> {
+        let account = self.account.read().await;
+        Ok(account.clone())
     }
 
     // 初始化方法
     pub async fn initialize(&self, app_handle: Option<&AppHandle>) -> Result<(), AccountError> {
         if let Some(handle) = app_handle {
+            // 先尝试从本地加密文件恢复上次的账号信息，deviceLogin会在请求成功后覆盖为最新状态
+            if let Some(account) = Self::load_account_from_disk(handle) {
+                {
+                    let mut guard = self.account.write().await;
+                    *guard = account;
+                }
+                println!("已从本地加密文件恢复账号信息");
+            }
+
             // 直接使用 deviceLogin
             self.device_login(handle).await?;
         }
         Ok(())
     }
 
+    // 从本地加密文件恢复账号信息；文件不存在、解密失败或内容损坏时返回None，不影响正常启动流程
+    fn load_account_from_disk(app_handle: &AppHandle) -> Option<Account> {
+        let account_file_path = get_account_file_path(app_handle).ok()?;
+        if !account_file_path.exists() {
+            return None;
+        }
+
+        let file_content = fs::read_to_string(&account_file_path).ok()?;
+        let (plaintext, is_legacy) = decrypt_at_rest(&file_content).ok()?;
+        let account: Account = serde_json::from_slice(&plaintext).ok()?;
+
+        if is_legacy {
+            println!("检测到明文账号文件，正在迁移为加密存储");
+            if let Err(e) = write_encrypted_file(&account_file_path, &plaintext) {
+                eprintln!("迁移账号文件加密失败: {}", e);
+            }
+        }
+
+        Some(account)
+    }
+
+    // 将账号信息加密后落盘，供下次启动时离线恢复登录状态
+    fn persist_account_to_disk(app_handle: &AppHandle, account: &Account) -> Result<(), AccountError> {
+        let account_file_path = get_account_file_path(app_handle)?;
+        let plaintext = serde_json::to_vec(account)?;
+        write_encrypted_file(&account_file_path, &plaintext)
+    }
+
     // 更新账号状态
     pub async fn update_account_status(&self, app_handle: &AppHandle) -> Result<(), AccountError> {
-        // 直接使用 deviceLogin 获取最新状态
-        self.device_login(app_handle).await
+        self.refresh_account(app_handle, FetchReason::Manual).await
     }
 
-    // deviceLogin 方法
+    // 统一的账号状态刷新入口，定时轮询和前端主动刷新都走这里；
+    // 状态发生变化时才向前端广播account-status-changed事件，避免无意义的高频事件
+    pub async fn refresh_account(&self, app_handle: &AppHandle, reason: FetchReason) -> Result<(), AccountError> {
+        println!("刷新账号状态，触发来源: {:?}", reason);
+        self.device_login(app_handle).await?;
+
+        let account = self.get_account().await?;
+        let changed = {
+            let mapped_status = AccountStatus::from_str(&account.status);
+            let mut last_status = self.last_status.write()
+                .map_err(|_| AccountError::Other("无法访问账号状态缓存".to_string()))?;
+            let changed = *last_status != Some(mapped_status);
+            *last_status = Some(mapped_status);
+            changed
+        };
+
+        if changed {
+            if let Err(e) = app_handle.emit("account-status-changed", account) {
+                eprintln!("发送account-status-changed事件失败: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // deviceLogin 方法。先换取一次性nonce，再用设备私钥签名"deviceId||nonce"证明身份，
+    // 这样服务端才能把deviceId和一把之前见过的公钥绑定起来，不能仅凭deviceId字符串冒认设备
     async fn device_login(&self, app_handle: &AppHandle) -> Result<(), AccountError> {
         let machine_id = self.get_machine_id(app_handle).await?;
         let hostname = Self::get_hostname();
-        
+        let nonce = self.request_challenge_nonce(&machine_id).await?;
+        let (public_key, signature) = self.sign_device_challenge(app_handle, &machine_id, &nonce)?;
+
         let request_body = serde_json::json!({
             "deviceId": machine_id,
-            "deviceName": hostname
+            "deviceName": hostname,
+            "nonce": nonce,
+            "publicKey": public_key,
+            "signature": signature
         });
 
         let response = self.client
@@ -244,44 +464,174 @@ impl AccountManager {
         }
 
         // 更新账号信息
-        self.update_account(api_response.data, Some(app_handle))?;
-        
+        self.update_account(api_response.data, Some(app_handle)).await?;
+
         Ok(())
     }
 
     // 更新账号信息
-    pub fn update_account(&self, mut new_account: Account, app_handle: Option<&AppHandle>) -> Result<(), AccountError> {
+    pub async fn update_account(&self, mut new_account: Account, app_handle: Option<&AppHandle>) -> Result<(), AccountError> {
+        // 设备列表与缓存共用同一次单调性校验：乱序/过期响应既不能进缓存，也不能覆盖account.devices，
+        // 否则get_account_info之类直接读取account.devices的命令会绕过缓存看到回退的花名册
+        if let Some(devices) = new_account.devices.clone() {
+            match self.update_devices_cache(devices) {
+                Ok(()) => {}
+                Err(e) => {
+                    eprintln!("拒绝更新设备列表缓存: {}", e);
+                    let previous_devices = self.account.read().await.devices.clone();
+                    new_account.devices = previous_devices;
+                }
+            }
+        }
+
         // 更新账号信息
-        if let Ok(mut account) = self.account.write() {
+        {
+            let mut account = self.account.write().await;
             *account = new_account.clone();
         }
+
+        // 落盘失败不影响内存中的账号状态，仅记录日志
+        if let Some(handle) = app_handle {
+            if let Err(e) = Self::persist_account_to_disk(handle, &new_account) {
+                eprintln!("保存账号信息到本地文件失败: {}", e);
+            }
+        }
+
         Ok(())
     }
 
-    // 获取当前设备信息
-    pub async fn get_current_device(&self, app_handle: &AppHandle) -> Result<Option<DeviceInfo>, AccountError> {
+    // 用refreshToken换取新的accessToken/refreshToken。refresh_lock保证并发命令不会同时各发一次刷新请求。
+    // 刷新本身失败（refreshToken也过期了）时，回退到device_login重新建立会话。
+    async fn refresh_access_token(&self, app_handle: &AppHandle, stale_token: &str) -> Result<(), AccountError> {
+        let _refresh_guard = self.refresh_lock.lock().await;
+
+        // 等锁期间别的命令已经完成了刷新，token已换新，不再重复刷新
+        if self.get_account().await?.accessToken.expose_secret() != stale_token {
+            return Ok(());
+        }
+
+        let refresh_token = self.get_account().await?.refreshToken.expose_secret().clone();
+        if refresh_token.is_empty() {
+            println!("没有refreshToken，回退到设备登录");
+            return self.device_login(app_handle).await;
+        }
+
+        let response = self.client
+            .post(&format!("{}/api/account/refresh", API_BASE_URL))
+            .json(&serde_json::json!({ "refreshToken": refresh_token }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            println!("刷新access_token失败，HTTP状态码: {}，回退到设备登录", response.status());
+            return self.device_login(app_handle).await;
+        }
+
+        let api_response: ApiResponse<Account> = response.json().await?;
+        if !api_response.success {
+            println!("刷新access_token失败: {}，回退到设备登录", api_response.errorMsg);
+            return self.device_login(app_handle).await;
+        }
+
+        self.update_account(api_response.data, Some(app_handle)).await?;
+        println!("access_token刷新成功");
+        Ok(())
+    }
+
+    // 用当前accessToken发送一次请求；如果服务端返回401，刷新token后原样重试一次。
+    // request_fn接收当前accessToken并构造/发送请求，以便重试时能换上新token。
+    pub async fn ensure_valid_token<F, Fut>(&self, app_handle: &AppHandle, request_fn: F) -> Result<reqwest::Response, AccountError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: std::future::Future<Output = reqwest::Result<reqwest::Response>>,
+    {
+        let access_token = self.get_account().await?.accessToken.expose_secret().clone();
+        let response = request_fn(access_token.clone()).await?;
+
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        println!("请求返回401，尝试刷新access_token后重试一次");
+        self.refresh_access_token(app_handle, &access_token).await?;
+        let refreshed_token = self.get_account().await?.accessToken.expose_secret().clone();
+        Ok(request_fn(refreshed_token).await?)
+    }
+
+    // 获取当前设备信息。ignore_cache为false时，DEVICES_FRESHNESS_THRESHOLD内会直接复用缓存而不触发网络请求
+    pub async fn get_current_device(&self, app_handle: &AppHandle, ignore_cache: bool) -> Result<Option<DeviceInfo>, AccountError> {
         let machine_id = self.get_machine_id(app_handle).await?;
-        let account = self.get_account()?;
-        
-        if let Some(devices) = account.devices {
-            for device in devices {
-                if device.deviceId == machine_id {
-                    return Ok(Some(device));
-                }
+        let devices = self.get_devices(app_handle, ignore_cache).await?;
+
+        Ok(devices.into_iter().find(|device| device.deviceId == machine_id))
+    }
+
+    // 获取设备列表。ignore_cache为false且缓存未过期时直接返回缓存，否则刷新账号状态后再读取
+    pub async fn get_devices(&self, app_handle: &AppHandle, ignore_cache: bool) -> Result<Vec<DeviceInfo>, AccountError> {
+        if !ignore_cache {
+            if let Some(devices) = self.cached_devices_if_fresh() {
+                return Ok(devices);
             }
         }
-        
-        Ok(None)
+
+        self.refresh_account(app_handle, FetchReason::Manual).await?;
+        Ok(self.cached_devices_if_fresh().unwrap_or_default())
+    }
+
+    // 缓存未过期时返回缓存内容，过期或为空返回None
+    fn cached_devices_if_fresh(&self) -> Option<Vec<DeviceInfo>> {
+        let cache = self.devices_cache.read().ok()?;
+        let cached = cache.as_ref()?;
+        if cached.cached_at.elapsed() < DEVICES_FRESHNESS_THRESHOLD {
+            Some(cached.devices.clone())
+        } else {
+            None
+        }
+    }
+
+    // 用新收到的设备列表更新缓存；如果新列表的最新在线时间比已缓存的更旧，判定为乱序/过期响应并拒绝覆盖
+    fn update_devices_cache(&self, new_devices: Vec<DeviceInfo>) -> Result<(), DeviceListError> {
+        let incoming_max = Self::max_last_online_time(&new_devices);
+
+        let mut cache = match self.devices_cache.write() {
+            Ok(guard) => guard,
+            Err(_) => return Ok(()), // 锁被污染时放弃这次缓存更新，不影响主账号状态
+        };
+
+        if let Some(existing) = cache.as_ref() {
+            let cached_max = Self::max_last_online_time(&existing.devices);
+            if !cached_max.is_empty() && incoming_max < cached_max {
+                return Err(DeviceListError {
+                    incoming_max_last_online_time: incoming_max,
+                    cached_max_last_online_time: cached_max,
+                });
+            }
+        }
+
+        *cache = Some(DevicesCache {
+            devices: new_devices,
+            cached_at: std::time::Instant::now(),
+        });
+        Ok(())
+    }
+
+    // 设备列表中最新的lastOnlineTime（ISO8601字符串，字典序即可比较先后）
+    fn max_last_online_time(devices: &[DeviceInfo]) -> String {
+        devices
+            .iter()
+            .map(|d| d.lastOnlineTime.as_str())
+            .max()
+            .unwrap_or("")
+            .to_string()
     }
 
     // 获取机器唯一ID
     pub async fn get_machine_id(&self, app_handle: &AppHandle) -> Result<String, AccountError> {
         // 首先尝试从内存缓存获取
         {
-            if let Ok(device_id_guard) = DEVICE_ID.read() {
-                if let Some(device_id) = device_id_guard.as_ref() {
-                    return Ok(device_id.clone());
-                }
+            let device_id_guard = DEVICE_ID.read().await;
+            if let Some(device_id) = device_id_guard.as_ref() {
+                return Ok(device_id.clone());
             }
         }
         
@@ -290,21 +640,37 @@ impl AccountManager {
         if device_id_file_path.exists() {
             match fs::read_to_string(&device_id_file_path) {
                 Ok(file_content) => {
-                    match serde_json::from_str::<serde_json::Value>(&file_content) {
-                        Ok(json_data) => {
-                            if let Some(device_id) = json_data.get("deviceId").and_then(|v| v.as_str()) {
-                                let device_id = device_id.to_string();
-                                
-                                // 将设备ID存入内存缓存
-                                if let Ok(mut device_id_guard) = DEVICE_ID.write() {
-                                    *device_id_guard = Some(device_id.clone());
+                    match decrypt_at_rest(&file_content) {
+                        Ok((plaintext, is_legacy)) => {
+                            match serde_json::from_slice::<serde_json::Value>(&plaintext) {
+                                Ok(json_data) => {
+                                    if let Some(device_id) = json_data.get("deviceId").and_then(|v| v.as_str()) {
+                                        let device_id = device_id.to_string();
+
+                                        // 将设备ID存入内存缓存
+                                        {
+                                            let mut device_id_guard = DEVICE_ID.write().await;
+                                            *device_id_guard = Some(device_id.clone());
+                                        }
+
+                                        // 旧版明文文件升级为加密存储
+                                        if is_legacy {
+                                            println!("检测到明文设备ID文件，正在迁移为加密存储");
+                                            if let Err(e) = write_encrypted_file(&device_id_file_path, &plaintext) {
+                                                eprintln!("迁移设备ID文件加密失败: {}", e);
+                                            }
+                                        }
+
+                                        return Ok(device_id);
+                                    }
+                                }
+                                Err(e) => {
+                                    eprintln!("解析设备ID文件失败: {}", e);
                                 }
-                                
-                                return Ok(device_id);
                             }
                         }
                         Err(e) => {
-                            eprintln!("解析设备ID文件失败: {}", e);
+                            eprintln!("解密设备ID文件失败: {}", e);
                         }
                     }
                 }
@@ -325,23 +691,113 @@ impl AccountManager {
         
         println!("使用machine-uuid生成设备ID: {}", device_id);
         
-        // 保存到文件
+        // 加密后保存到文件
         let json_data = serde_json::json!({
             "deviceId": device_id
         });
-        
-        if let Err(e) = fs::write(&device_id_file_path, serde_json::to_string_pretty(&json_data).unwrap_or_default()) {
+        let plaintext = serde_json::to_vec(&json_data).unwrap_or_default();
+
+        if let Err(e) = write_encrypted_file(&device_id_file_path, &plaintext) {
             eprintln!("保存设备ID到文件失败: {}", e);
         }
         
         // 保存到内存缓存
-        if let Ok(mut device_id_guard) = DEVICE_ID.write() {
+        {
+            let mut device_id_guard = DEVICE_ID.write().await;
             *device_id_guard = Some(device_id.clone());
         }
-        
+
         Ok(device_id)
     }
 
+    // 获取（或首次生成）设备的Ed25519身份密钥对。私钥加密落盘、内存缓存SigningKey，避免每次签名都解密一次
+    fn get_or_create_device_keypair(&self, app_handle: &AppHandle) -> Result<SigningKey, AccountError> {
+        if let Ok(guard) = DEVICE_KEYPAIR.read() {
+            if let Some(signing_key) = guard.as_ref() {
+                return Ok(signing_key.clone());
+            }
+        }
+
+        if let Some(signing_key) = Self::load_device_keypair_from_disk(app_handle) {
+            if let Ok(mut guard) = DEVICE_KEYPAIR.write() {
+                *guard = Some(signing_key.clone());
+            }
+            return Ok(signing_key);
+        }
+
+        println!("未找到设备身份密钥，正在生成新的Ed25519密钥对");
+        let signing_key = SigningKey::generate(&mut OsRng);
+        Self::persist_device_keypair(app_handle, &signing_key)?;
+
+        if let Ok(mut guard) = DEVICE_KEYPAIR.write() {
+            *guard = Some(signing_key.clone());
+        }
+
+        Ok(signing_key)
+    }
+
+    // 从加密文件读取设备私钥（32字节seed，base64编码后加密存储）
+    fn load_device_keypair_from_disk(app_handle: &AppHandle) -> Option<SigningKey> {
+        let key_file_path = get_device_key_file_path(app_handle).ok()?;
+        if !key_file_path.exists() {
+            return None;
+        }
+
+        let file_content = fs::read_to_string(&key_file_path).ok()?;
+        let (plaintext, _) = decrypt_at_rest(&file_content).ok()?;
+        let json_data: serde_json::Value = serde_json::from_slice(&plaintext).ok()?;
+        let seed_b64 = json_data.get("seed").and_then(|v| v.as_str())?;
+        let seed_bytes = BASE64.decode(seed_b64).ok()?;
+        let seed: [u8; 32] = seed_bytes.try_into().ok()?;
+
+        Some(SigningKey::from_bytes(&seed))
+    }
+
+    // 将设备私钥加密后落盘
+    fn persist_device_keypair(app_handle: &AppHandle, signing_key: &SigningKey) -> Result<(), AccountError> {
+        let key_file_path = get_device_key_file_path(app_handle)?;
+        let json_data = serde_json::json!({
+            "seed": BASE64.encode(signing_key.to_bytes())
+        });
+        let plaintext = serde_json::to_vec(&json_data)?;
+        write_encrypted_file(&key_file_path, &plaintext)
+    }
+
+    // 向服务端换取一次性挑战nonce，deviceLogin和unbind_device在证明设备身份前都要先拿到它
+    async fn request_challenge_nonce(&self, machine_id: &str) -> Result<String, AccountError> {
+        let response = self.client
+            .post(&format!("{}/api/account/deviceLoginChallenge", API_BASE_URL))
+            .json(&serde_json::json!({ "deviceId": machine_id }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(AccountError::ApiError(format!("获取登录挑战失败: {}", response.status())));
+        }
+
+        let api_response: ApiResponse<serde_json::Value> = response.json().await?;
+        if !api_response.success {
+            return Err(AccountError::ApiError(api_response.errorMsg));
+        }
+
+        api_response.data.get("nonce")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| AccountError::Other("登录挑战响应缺少nonce字段".to_string()))
+    }
+
+    // 用设备私钥对"deviceId||nonce"签名，返回(base64公钥, base64签名)供请求体携带
+    fn sign_device_challenge(&self, app_handle: &AppHandle, device_id: &str, nonce: &str) -> Result<(String, String), AccountError> {
+        let signing_key = self.get_or_create_device_keypair(app_handle)?;
+        let message = format!("{}{}", device_id, nonce);
+        let signature = signing_key.sign(message.as_bytes());
+
+        Ok((
+            BASE64.encode(signing_key.verifying_key().to_bytes()),
+            BASE64.encode(signature.to_bytes()),
+        ))
+    }
+
     // 辅助函数：获取主机名（不带EasyVPN后缀）
     fn get_hostname() -> String {
         match hostname::get() {
@@ -367,22 +823,45 @@ pub fn get_account_manager() -> Arc<AccountManager> {
 // 初始化账号管理器并启动定时更新任务
 pub async fn initialize_account(app_handle: tauri::AppHandle) -> Result<(), AccountError> {
     let account_manager = get_account_manager();
-    
+
     // 尝试初始化账号
-    match account_manager.initialize(Some(&app_handle)).await {
-        Ok(_) => {
-            Ok(())
-        },
+    let result = match account_manager.initialize(Some(&app_handle)).await {
+        Ok(_) => Ok(()),
         Err(e) => {
             eprintln!("初始化账号失败: {}", e);
             Ok(()) // 即使失败也返回Ok让应用继续启动
         }
+    };
+
+    spawn_status_polling(app_handle);
+
+    result
+}
+
+// 启动后台定时刷新账号状态的任务；整个应用生命周期内只会真正启动一次
+fn spawn_status_polling(app_handle: tauri::AppHandle) {
+    if POLLING_STARTED.swap(true, std::sync::atomic::Ordering::SeqCst) {
+        return;
     }
+
+    tauri::async_runtime::spawn(async move {
+        let account_manager = get_account_manager();
+        let mut ticker = interval(Duration::from_secs(STATUS_POLL_INTERVAL_SECS));
+        // 第一次tick会立即触发，initialize中已经做过一次登录/刷新了，这里跳过
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            if let Err(e) = account_manager.refresh_account(&app_handle, FetchReason::Poll).await {
+                eprintln!("定时刷新账号状态失败: {}", e);
+            }
+        }
+    });
 }
 
 // 提供给其他模块获取当前账号信息的函数
 pub async fn get_current_account() -> Result<Account, AccountError> {
-    get_account_manager().get_account()
+    get_account_manager().get_account().await
 }
 
 // 定义用户登录请求结构体
@@ -457,7 +936,7 @@ pub async fn login(auth: AuthRequest, app_handle: AppHandle) -> Result<String, S
     };
     
     // 更新账号信息
-    match account_manager.update_account(account_data.clone(), Some(&app_handle)) {
+    match account_manager.update_account(account_data.clone(), Some(&app_handle)).await {
         Ok(_) => {
             println!("登录成功，账号信息已更新");
             
@@ -477,18 +956,14 @@ pub async fn login(auth: AuthRequest, app_handle: AppHandle) -> Result<String, S
 pub async fn logout(app_handle: AppHandle) -> Result<String, String> {
     let account_manager = get_account_manager();
     
-    // 获取当前账号的访问令牌和刷新令牌
-    let (access_token, refresh_token) = {
-        let account_read = match account_manager.account.read() {
-            Ok(guard) => guard,
-            Err(_) => return Err("无法读取账号信息".to_string()),
-        };
-        
-        (account_read.accessToken.clone(), account_read.refreshToken.clone())
+    // 获取当前的刷新令牌；accessToken由ensure_valid_token内部按需读取/刷新
+    let refresh_token = {
+        let account_read = account_manager.account.read().await;
+        account_read.refreshToken.expose_secret().clone()
     };
     
     // 获取当前设备信息
-    let device_user_id = match account_manager.get_current_device(&app_handle).await {
+    let device_user_id = match account_manager.get_current_device(&app_handle, false).await {
         Ok(Some(device)) => device.deviceUserId.to_string(),
         Ok(None) => {
             return Err("未找到当前设备信息".to_string());
@@ -498,21 +973,23 @@ pub async fn logout(app_handle: AppHandle) -> Result<String, String> {
     
     // 创建HTTP客户端
     let client = Client::new();
-    
+
     // 使用URL参数方式传递deviceUserId和refreshToken
-    let url = format!("{}/api/account/logout?deviceUserId={}&refreshToken={}", API_BASE_URL, 
+    let url = format!("{}/api/account/logout?deviceUserId={}&refreshToken={}", API_BASE_URL,
     device_user_id, refresh_token);
-    
-    // 发送请求到API端点
-    let response = match client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await {
-            Ok(resp) => resp,
-            Err(e) => return Err(format!("网络请求失败: {}", e)),
-        };
-    
+
+    // 发送请求到API端点；access_token过期时(401)自动刷新后重试一次
+    let response = match account_manager.ensure_valid_token(&app_handle, |token| {
+        let client = client.clone();
+        let url = url.clone();
+        async move {
+            client.post(&url).header("Authorization", format!("Bearer {}", token)).send().await
+        }
+    }).await {
+        Ok(resp) => resp,
+        Err(e) => return Err(format!("网络请求失败: {}", e)),
+    };
+
     // 检查HTTP状态码
     if !response.status().is_success() {
         return Err(format!("退出登录失败，HTTP状态码: {}", response.status()));
@@ -556,35 +1033,54 @@ pub async fn logout(app_handle: AppHandle) -> Result<String, String> {
 #[tauri::command]
 pub async fn unbind_device(device_user_id: String, current_device_user_id: Option<String>, app_handle: AppHandle) -> Result<String, String> {
     let account_manager = get_account_manager();
-    let access_token = match account_manager.account.read() {
-        Ok(account) => account.accessToken.clone(),
-        Err(_) => return Err("无法读取账号信息".to_string()),
+    let access_token = {
+        let account = account_manager.account.read().await;
+        account.accessToken.expose_secret().clone()
     };
-    
+
     if access_token.is_empty() {
         return Err("未登录状态，请先登录".to_string());
-    }   
-    
+    }
+
     let client = Client::new();
-    
+
+    // 和deviceLogin一样签名，证明这次解绑请求确实来自持有设备私钥的那台机器
+    let machine_id = match account_manager.get_machine_id(&app_handle).await {
+        Ok(id) => id,
+        Err(e) => return Err(format!("获取设备ID失败: {}", e)),
+    };
+    let nonce = match account_manager.request_challenge_nonce(&machine_id).await {
+        Ok(n) => n,
+        Err(e) => return Err(format!("获取解绑挑战失败: {}", e)),
+    };
+    let (public_key, signature) = match account_manager.sign_device_challenge(&app_handle, &machine_id, &nonce) {
+        Ok(pair) => pair,
+        Err(e) => return Err(format!("签名解绑请求失败: {}", e)),
+    };
+
     // 构建URL参数
-    let mut url = format!("{}/api/account/unbind-device?deviceUserId={}", API_BASE_URL, device_user_id);
-    
+    let mut url = format!(
+        "{}/api/account/unbind-device?deviceUserId={}&nonce={}&publicKey={}&signature={}",
+        API_BASE_URL, device_user_id, nonce, public_key, signature
+    );
+
     // 如果current_device_user_id有值，添加到URL参数
     if let Some(current_id) = current_device_user_id {
         url = format!("{}&currentDeviceUserId={}", url, current_id);
     }
-    
-    // 发送请求
-    let response = match client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await {
-            Ok(resp) => resp,
-            Err(e) => return Err(format!("网络请求失败: {}", e)),
-        };
-        
+
+    // 发送请求；access_token过期时(401)自动刷新后重试一次
+    let response = match account_manager.ensure_valid_token(&app_handle, |token| {
+        let client = client.clone();
+        let url = url.clone();
+        async move {
+            client.post(&url).header("Authorization", format!("Bearer {}", token)).send().await
+        }
+    }).await {
+        Ok(resp) => resp,
+        Err(e) => return Err(format!("网络请求失败: {}", e)),
+    };
+
     // 检查HTTP状态码
     if !response.status().is_success() {
         return Err(format!("解绑设备失败，HTTP状态码: {}", response.status()));
@@ -620,15 +1116,37 @@ pub async fn unbind_device(device_user_id: String, current_device_user_id: Optio
 
 // 新增Tauri命令，用于获取当前设备信息
 #[tauri::command]
-pub async fn get_current_device_info(app_handle: AppHandle) -> Result<Option<DeviceInfo>, String> {
+pub async fn get_current_device_info(app_handle: AppHandle, ignore_cache: bool) -> Result<Option<DeviceInfo>, String> {
     let account_manager = get_account_manager();
-    
-    match account_manager.get_current_device(&app_handle).await {
+
+    match account_manager.get_current_device(&app_handle, ignore_cache).await {
         Ok(device) => Ok(device),
         Err(e) => Err(format!("获取当前设备信息失败: {}", e)),
     }
 }
 
+// 获取设备身份公钥（base64），供前端展示指纹，方便用户核对是不是自己的设备
+#[tauri::command]
+pub async fn get_device_public_key(app_handle: AppHandle) -> Result<String, String> {
+    let account_manager = get_account_manager();
+
+    account_manager
+        .get_or_create_device_keypair(&app_handle)
+        .map(|signing_key| BASE64.encode(signing_key.verifying_key().to_bytes()))
+        .map_err(|e| format!("获取设备公钥失败: {}", e))
+}
+
+// 获取设备列表，默认在DEVICES_FRESHNESS_THRESHOLD内复用缓存，传入ignore_cache=true可强制刷新
+#[tauri::command]
+pub async fn list_devices(app_handle: AppHandle, ignore_cache: bool) -> Result<Vec<DeviceInfo>, String> {
+    let account_manager = get_account_manager();
+
+    account_manager
+        .get_devices(&app_handle, ignore_cache)
+        .await
+        .map_err(|e| format!("获取设备列表失败: {}", e))
+}
+
 // 更新并获取账号信息
 #[tauri::command]
 pub async fn update_and_get_account(app_handle: AppHandle) -> Result<Account, String> {
@@ -640,7 +1158,7 @@ pub async fn update_and_get_account(app_handle: AppHandle) -> Result<Account, St
     }
     
     // 获取更新后的账号信息
-    match account_manager.get_account() {
+    match account_manager.get_account().await {
         Ok(account) => Ok(account),
         Err(e) => Err(format!("获取账号信息失败: {}", e)),
     }
@@ -1,7 +1,10 @@
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Mutex;
 use std::io::Result;
-use tauri::{Wry, AppHandle, Manager};
+use std::time::Duration;
+use tauri::{Wry, AppHandle, Manager, Emitter};
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
 use once_cell::sync::Lazy;
@@ -16,6 +19,252 @@ const CLASH_API_PORT: u16 = 9090;
 const CLASH_PROXY_PORT: u16 = 7890;
 const CLASH_SOCKS_PORT: u16 = 7891;
 
+// 记录一次成功启动所用到的路径，崩溃重启时原样复用
+#[derive(Debug, Clone)]
+struct ClashLaunchParams {
+    bin_path: PathBuf,
+    config_path: PathBuf,
+    log_dir: PathBuf,
+}
+
+static CLASH_LAUNCH_PARAMS: Lazy<Mutex<Option<ClashLaunchParams>>> = Lazy::new(|| Mutex::new(None));
+
+// 主动调用stop_clash时置位，supervisor据此区分"主动停止"和"意外崩溃"
+static INTENTIONAL_STOP: AtomicBool = AtomicBool::new(false);
+
+// 保证supervisor任务只被启动一次
+static SUPERVISOR_STARTED: AtomicBool = AtomicBool::new(false);
+
+// supervisor当前的重启尝试计数，由start_clash在每次全新启动时清零，
+// 这样手动重连后的核心崩溃又能从第一次退避重新计起，而不是延续上一轮耗尽的计数
+static RESTART_ATTEMPT: AtomicU32 = AtomicU32::new(0);
+
+// 崩溃重启的退避参数
+const SUPERVISOR_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+const RESTART_BASE_DELAY_MS: u64 = 900;
+const RESTART_MAX_DELAY_MS: u64 = 900 * 8;
+const RESTART_MAX_ATTEMPTS: u32 = 5;
+
+// 停止Clash时，优雅关闭的超时时间
+const GRACEFUL_STOP_TIMEOUT: Duration = Duration::from_millis(3000);
+const GRACEFUL_STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// 从config.yaml解析出的运行期设置：混合端口、socks端口、external-controller、secret。
+// 这些值实际由用户的config.yaml决定，不能假设和上面的常量一致。
+#[derive(Debug, Clone)]
+pub struct ClashRuntimeConfig {
+    pub mixed_port: u16,
+    pub socks_port: u16,
+    pub controller_host: String,
+    pub controller_port: u16,
+    pub secret: String,
+}
+
+impl Default for ClashRuntimeConfig {
+    fn default() -> Self {
+        Self {
+            mixed_port: CLASH_PROXY_PORT,
+            socks_port: CLASH_SOCKS_PORT,
+            controller_host: "127.0.0.1".to_string(),
+            controller_port: CLASH_API_PORT,
+            secret: String::new(),
+        }
+    }
+}
+
+impl ClashRuntimeConfig {
+    fn controller_base_url(&self) -> String {
+        format!("http://{}:{}", self.controller_host, self.controller_port)
+    }
+}
+
+static CLASH_RUNTIME_CONFIG: Lazy<Mutex<ClashRuntimeConfig>> =
+    Lazy::new(|| Mutex::new(ClashRuntimeConfig::default()));
+
+// 供其它模块（如set_mode/get_status）读取当前生效的端口/secret
+pub fn get_runtime_config() -> ClashRuntimeConfig {
+    CLASH_RUNTIME_CONFIG.lock().unwrap().clone()
+}
+
+fn is_valid_port(port: u16) -> bool {
+    port > 0
+}
+
+// 代理直连例外（bypass/no-proxy）列表：支持主机名和CIDR写法，默认排除回环与三段RFC1918私网地址
+fn default_bypass_list() -> Vec<String> {
+    vec![
+        "localhost".to_string(),
+        "127.0.0.0/8".to_string(),
+        "10.0.0.0/8".to_string(),
+        "172.16.0.0/12".to_string(),
+        "192.168.0.0/16".to_string(),
+    ]
+}
+
+static PROXY_BYPASS_LIST: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(default_bypass_list()));
+
+pub fn set_proxy_bypass_list(list: Vec<String>) {
+    *PROXY_BYPASS_LIST.lock().unwrap() = list;
+}
+
+pub fn get_proxy_bypass_list() -> Vec<String> {
+    PROXY_BYPASS_LIST.lock().unwrap().clone()
+}
+
+// 把一条bypass规则展开为平台认可的通配符/主机形式。
+// 只有字节对齐的前缀(/8、/16、/24)能精确表达为通配符；/12按/16近似处理，只覆盖首个/16网段
+// （172.16.0.0/12 -> 172.16.*）。其它前缀以及已经是域名/通配符的条目原样返回。
+fn cidr_to_wildcard(entry: &str) -> String {
+    if let Some((addr, prefix_str)) = entry.split_once('/') {
+        if let (Ok(prefix), Ok(ip)) = (prefix_str.parse::<u32>(), addr.parse::<std::net::Ipv4Addr>()) {
+            let octets = ip.octets();
+            return match prefix {
+                8 => format!("{}.*", octets[0]),
+                12 | 16 => format!("{}.{}.*", octets[0], octets[1]),
+                24 => format!("{}.{}.{}.*", octets[0], octets[1], octets[2]),
+                _ => entry.to_string(),
+            };
+        }
+    }
+    entry.to_string()
+}
+
+fn expanded_bypass_list() -> Vec<String> {
+    get_proxy_bypass_list().iter().map(|e| cidr_to_wildcard(e)).collect()
+}
+
+// gsettings的ignore-hosts是一个GVariant字符串数组字面量，形如['a','b']
+fn gsettings_ignore_hosts_literal() -> String {
+    let quoted: Vec<String> = expanded_bypass_list().iter().map(|h| format!("'{}'", h)).collect();
+    format!("[{}]", quoted.join(","))
+}
+
+// 读取config.yaml，解析出端口/controller/secret，并对缺失或非法的字段填充默认值，
+// 把“净化”后的结果写回文件，保证core和app使用的是同一份设置。
+fn load_and_guard_config(config_path: &Path) -> Result<ClashRuntimeConfig> {
+    let content = std::fs::read_to_string(config_path)?;
+    let mut doc: serde_yaml::Value = serde_yaml::from_str(&content).map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("解析config.yaml失败: {}", e))
+    })?;
+
+    let mapping = doc.as_mapping_mut().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "config.yaml根节点不是一个映射")
+    })?;
+
+    let default_cfg = ClashRuntimeConfig::default();
+    // 只有真的需要纠正某个字段时才回写文件，避免每次启动/每次导入订阅都用serde_yaml::to_string
+    // 整份重新序列化，把用户config.yaml里的注释和格式全部抹掉
+    let mut dirty = false;
+
+    // mixed-port优先，其次是port，都没有或非法就用默认值
+    let had_legacy_port_key = mapping.contains_key("port");
+    let existing_mixed_port = mapping.get("mixed-port").and_then(|v| v.as_u64());
+    let mixed_port = mapping
+        .get("mixed-port")
+        .or_else(|| mapping.get("port"))
+        .and_then(|v| v.as_u64())
+        .and_then(|v| u16::try_from(v).ok())
+        .filter(|p| is_valid_port(*p))
+        .unwrap_or(default_cfg.mixed_port);
+    if existing_mixed_port != Some(mixed_port as u64) {
+        mapping.insert("mixed-port".into(), (mixed_port as u64).into());
+        dirty = true;
+    }
+    // 统一成mixed-port后，旧的port键必须删掉，否则两个键各自被改写后可能互相矛盾
+    if had_legacy_port_key {
+        mapping.remove("port");
+        dirty = true;
+    }
+
+    let existing_socks_port = mapping.get("socks-port").and_then(|v| v.as_u64());
+    let socks_port = mapping
+        .get("socks-port")
+        .and_then(|v| v.as_u64())
+        .and_then(|v| u16::try_from(v).ok())
+        .filter(|p| is_valid_port(*p))
+        .unwrap_or(default_cfg.socks_port);
+    if existing_socks_port != Some(socks_port as u64) {
+        mapping.insert("socks-port".into(), (socks_port as u64).into());
+        dirty = true;
+    }
+
+    // external-controller格式为"host:port"，缺失或格式不对时回退到默认值
+    let existing_controller = mapping.get("external-controller").and_then(|v| v.as_str()).map(str::to_string);
+    let (controller_host, controller_port) = mapping
+        .get("external-controller")
+        .and_then(|v| v.as_str())
+        .and_then(|s| s.rsplit_once(':'))
+        .and_then(|(host, port)| {
+            let host = if host.is_empty() { "127.0.0.1" } else { host };
+            port.parse::<u16>().ok().filter(|p| is_valid_port(*p)).map(|p| (host.to_string(), p))
+        })
+        .unwrap_or((default_cfg.controller_host.clone(), default_cfg.controller_port));
+    let normalized_controller = format!("{}:{}", controller_host, controller_port);
+    if existing_controller.as_deref() != Some(normalized_controller.as_str()) {
+        mapping.insert("external-controller".into(), normalized_controller.into());
+        dirty = true;
+    }
+
+    let existing_secret = mapping.get("secret").and_then(|v| v.as_str()).map(str::to_string);
+    let secret = existing_secret.clone().unwrap_or_else(|| default_cfg.secret.clone());
+    if existing_secret.as_deref() != Some(secret.as_str()) {
+        mapping.insert("secret".into(), secret.clone().into());
+        dirty = true;
+    }
+
+    if dirty {
+        let sanitized = serde_yaml::to_string(&doc).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("序列化config.yaml失败: {}", e))
+        })?;
+        std::fs::write(config_path, sanitized)?;
+    }
+
+    let runtime_config = ClashRuntimeConfig {
+        mixed_port,
+        socks_port,
+        controller_host,
+        controller_port,
+        secret,
+    };
+
+    *CLASH_RUNTIME_CONFIG.lock().unwrap() = runtime_config.clone();
+    println!(
+        "config.yaml已校验: mixed-port={}, socks-port={}, controller={}:{}, secret={}",
+        runtime_config.mixed_port,
+        runtime_config.socks_port,
+        runtime_config.controller_host,
+        runtime_config.controller_port,
+        if runtime_config.secret.is_empty() { "(空)" } else { "(已设置)" }
+    );
+
+    Ok(runtime_config)
+}
+
+// 把一份订阅YAML写入config.yaml并通过load_and_guard_config校验/合并，成为下次启动Clash时生效的配置
+pub fn import_subscription_config(app_handle: &AppHandle<Wry>, yaml_content: &str) -> Result<PathBuf> {
+    let resource_path = app_handle.path().resource_dir().map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, format!("无法获取资源路径: {:?}", e))
+    })?;
+
+    let config_dir = if resource_path.to_string_lossy().contains("resources") {
+        resource_path.join("config")
+    } else {
+        resource_path.join("resources").join("config")
+    };
+
+    if !config_dir.exists() {
+        std::fs::create_dir_all(&config_dir)?;
+    }
+
+    let config_path = config_dir.join("config.yaml");
+    std::fs::write(&config_path, yaml_content)?;
+
+    // 复用启动前的同一套校验/合并逻辑，确保订阅导入的配置和正常启动时一样可信
+    load_and_guard_config(&config_path)?;
+
+    Ok(config_path)
+}
+
 // Clash模式枚举
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClashMode {
@@ -24,23 +273,74 @@ pub enum ClashMode {
     Direct,
 }
 
-// 启动Clash并设置系统代理
-pub fn start_clash_and_proxy(app_handle: &AppHandle<Wry>) -> Result<()> {
-    match start_clash(app_handle) {
-        Ok(_) => println!("start_clash成功执行"),
-        Err(e) => {
-            println!("start_clash执行失败: {:?}", e);
-            return Err(e);
+// TUN模式下使用的网络栈实现
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TunStack {
+    System,
+    Gvisor,
+}
+
+impl TunStack {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Gvisor => "gvisor",
         }
     }
-    
-    match set_system_proxy(true) {
-        Ok(_) => println!("系统代理设置成功"),
-        Err(e) => {
-            println!("系统代理设置失败: {:?}", e);
-            return Err(e);
+}
+
+// 记录当前是否已经通过Clash API开启了TUN（透明代理）模式。
+// 开启期间set_system_proxy不应再设置系统HTTP/SOCKS代理，否则两条路径会互相冲突。
+static TUN_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// 启动序列的scope guard：只要在guard被disarm前因为任何原因（早退/panic展开）离开这个函数，
+// 就把已经拉起的Clash进程杀掉、系统代理改回关闭，不留下"进程在跑但代理没配好"的中间状态。
+struct ClashStartupGuard {
+    armed: bool,
+}
+
+impl ClashStartupGuard {
+    fn new() -> Self {
+        Self { armed: true }
+    }
+
+    fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for ClashStartupGuard {
+    fn drop(&mut self) {
+        if self.armed {
+            println!("Clash启动序列未完整成功，回滚进程和系统代理设置");
+            if let Err(e) = stop_clash() {
+                println!("回滚时停止Clash失败: {:?}", e);
+            }
+            if let Err(e) = set_system_proxy(false) {
+                println!("回滚时关闭系统代理失败: {:?}", e);
+            }
         }
     }
+}
+
+// 启动Clash并设置系统代理
+pub fn start_clash_and_proxy(app_handle: &AppHandle<Wry>) -> Result<()> {
+    let mut guard = ClashStartupGuard::new();
+
+    if let Err(e) = start_clash(app_handle) {
+        println!("start_clash执行失败: {:?}", e);
+        return Err(e);
+    }
+    println!("start_clash成功执行");
+
+    if let Err(e) = set_system_proxy(true) {
+        println!("系统代理设置失败: {:?}", e);
+        return Err(e);
+    }
+    println!("系统代理设置成功");
+
+    // 启动序列全部成功，解除guard，避免把刚配置好的系统代理又回滚掉
+    guard.disarm();
     Ok(())
 }
 
@@ -130,44 +430,147 @@ fn start_clash(app_handle: &AppHandle<Wry>) -> Result<()> {
         return Err(err);
     }
     
+    // 解析并校验config.yaml，确保core和app对端口/controller/secret的认知一致
+    load_and_guard_config(&config_path)?;
+
     // 启动Clash进程
     println!("启动Clash进程...");
-    
-    let child = match Command::new(&clash_bin_path)
+    let log_dir = resource_path.join("logs");
+    let child = spawn_clash_child(&clash_bin_path, &config_path, &log_dir)?;
+
+    // 存储进程
+    *clash_lock = Some(child);
+
+    // 记录本次启动参数，供supervisor在崩溃后原样重启
+    *CLASH_LAUNCH_PARAMS.lock().unwrap() = Some(ClashLaunchParams {
+        bin_path: clash_bin_path,
+        config_path,
+        log_dir,
+    });
+    INTENTIONAL_STOP.store(false, Ordering::SeqCst);
+
+    // 全新启动，重置重启计数，避免沿用上一轮耗尽的退避次数
+    RESTART_ATTEMPT.store(0, Ordering::SeqCst);
+
+    // 释放锁后再启动supervisor，避免与它的首次轮询互相等待
+    drop(clash_lock);
+    spawn_supervisor(app_handle.clone());
+
+    println!("Clash已启动");
+    Ok(())
+}
+
+// 实际拉起Clash子进程，start_clash和supervisor重启时共用
+fn spawn_clash_child(bin_path: &Path, config_path: &Path, log_dir: &Path) -> Result<Child> {
+    match Command::new(bin_path)
         .arg("-f")
-        .arg(&config_path)
+        .arg(config_path)
         .arg("-d")
-        .arg(resource_path.join("logs"))
+        .arg(log_dir)
         .spawn() {
             Ok(c) => {
                 println!("Clash进程启动成功, PID: {:?}", c.id());
-                c
+                Ok(c)
             },
             Err(e) => {
                 println!("Clash进程启动失败: {:?}", e);
-                return Err(e);
+                Err(e)
             }
-        };
-    
-    // 存储进程
-    *clash_lock = Some(child);
-    
-    println!("Clash已启动");
-    Ok(())
+        }
+}
+
+// 监控Clash子进程，意外退出时按退避策略自动重启
+fn spawn_supervisor(app_handle: AppHandle<Wry>) {
+    if SUPERVISOR_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SUPERVISOR_POLL_INTERVAL).await;
+
+            let exited = {
+                let mut clash_lock = CLASH_PROCESS.lock().unwrap();
+                match clash_lock.as_mut() {
+                    Some(child) => match child.try_wait() {
+                        Ok(Some(status)) => {
+                            println!("supervisor检测到Clash已退出，状态码: {:?}", status.code());
+                            *clash_lock = None;
+                            true
+                        },
+                        _ => false,
+                    },
+                    None => false,
+                }
+            };
+
+            if !exited {
+                continue;
+            }
+
+            if INTENTIONAL_STOP.swap(false, Ordering::SeqCst) {
+                println!("supervisor: 这是主动停止，不触发崩溃恢复");
+                RESTART_ATTEMPT.store(0, Ordering::SeqCst);
+                continue;
+            }
+
+            let attempt = RESTART_ATTEMPT.fetch_add(1, Ordering::SeqCst) + 1;
+            if attempt > RESTART_MAX_ATTEMPTS {
+                println!("supervisor: 已达到最大重启次数({})，放弃自动恢复", RESTART_MAX_ATTEMPTS);
+                let _ = app_handle.emit("core-restarting", serde_json::json!({
+                    "status": "gave_up",
+                    "attempt": attempt,
+                }));
+                continue;
+            }
+
+            let delay_ms = RESTART_BASE_DELAY_MS
+                .saturating_mul(1u64 << (attempt - 1).min(4))
+                .min(RESTART_MAX_DELAY_MS);
+
+            println!("supervisor: Clash意外退出，{}ms后进行第{}次重启", delay_ms, attempt);
+            let _ = app_handle.emit("core-restarting", serde_json::json!({
+                "status": "restarting",
+                "attempt": attempt,
+                "delay_ms": delay_ms,
+            }));
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+
+            let params = CLASH_LAUNCH_PARAMS.lock().unwrap().clone();
+            let Some(params) = params else {
+                println!("supervisor: 没有缓存的启动参数，无法自动重启");
+                continue;
+            };
+
+            match spawn_clash_child(&params.bin_path, &params.config_path, &params.log_dir) {
+                Ok(child) => {
+                    *CLASH_PROCESS.lock().unwrap() = Some(child);
+                    println!("supervisor: Clash重启成功");
+                    let _ = app_handle.emit("core-restarting", serde_json::json!({
+                        "status": "recovered",
+                    }));
+                },
+                Err(e) => {
+                    println!("supervisor: Clash重启失败: {:?}", e);
+                }
+            }
+        }
+    });
 }
 
 // 停止Clash
 fn stop_clash() -> Result<()> {
     let mut clash_lock = CLASH_PROCESS.lock().unwrap();
-    
+
     if let Some(ref mut child) = *clash_lock {
         println!("停止Clash...");
+        // 标记为主动停止，supervisor看到进程退出时不会当作崩溃处理
+        INTENTIONAL_STOP.store(true, Ordering::SeqCst);
+
         // 发送终止信号
         #[cfg(not(target_os = "windows"))]
-        {
-            unsafe { libc::kill(child.id() as i32, libc::SIGTERM); }
-        }
-        
+        unsafe { libc::kill(child.id() as i32, libc::SIGTERM); }
+
         #[cfg(target_os = "windows")]
         {
             // Windows上使用taskkill命令终止进程
@@ -175,39 +578,69 @@ fn stop_clash() -> Result<()> {
                 .args(&["/F", "/T", "/PID", &child.id().to_string()])
                 .spawn();
         }
-        
-        // 等待进程退出
-        let _ = child.wait();
+
+        // 限时等待进程退出，避免核心卡死时UI被无限期阻塞
+        let mut waited = Duration::from_millis(0);
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    println!("Clash已退出，状态码: {:?}", status.code());
+                    break;
+                },
+                Ok(None) => {
+                    if waited >= GRACEFUL_STOP_TIMEOUT {
+                        println!("Clash在{:?}内未响应终止信号，强制SIGKILL", GRACEFUL_STOP_TIMEOUT);
+                        #[cfg(not(target_os = "windows"))]
+                        unsafe { libc::kill(child.id() as i32, libc::SIGKILL); }
+                        let _ = child.wait();
+                        break;
+                    }
+                    std::thread::sleep(GRACEFUL_STOP_POLL_INTERVAL);
+                    waited += GRACEFUL_STOP_POLL_INTERVAL;
+                },
+                Err(e) => {
+                    println!("等待Clash退出时出错: {:?}", e);
+                    break;
+                }
+            }
+        }
         println!("Clash已停止");
     }
-    
+
     // 清除进程引用
     *clash_lock = None;
-    
+
     Ok(())
 }
 
 // 设置系统代理
 fn set_system_proxy(enable: bool) -> Result<()> {
+    // TUN模式已经在网络层接管了流量，不要再叠加系统HTTP/SOCKS代理
+    if enable && TUN_ACTIVE.load(Ordering::SeqCst) {
+        println!("TUN模式已开启，跳过系统代理设置");
+        return Ok(());
+    }
+
     println!("{}系统代理...", if enable { "启用" } else { "禁用" });
-    
+    let runtime_config = get_runtime_config();
+
     // 根据操作系统执行不同的命令
     #[cfg(target_os = "macos")]
     {
         if enable {
             // 设置HTTP代理
             let _ = Command::new("networksetup")
-                .args(&["-setwebproxy", "Wi-Fi", "127.0.0.1", &CLASH_PROXY_PORT.to_string()])
+                .args(&["-setwebproxy", "Wi-Fi", "127.0.0.1", &runtime_config.mixed_port.to_string()])
                 .output()?;
-                
+
             // 设置HTTPS代理
             let _ = Command::new("networksetup")
-                .args(&["-setsecurewebproxy", "Wi-Fi", "127.0.0.1", &CLASH_PROXY_PORT.to_string()])
+                .args(&["-setsecurewebproxy", "Wi-Fi", "127.0.0.1", &runtime_config.mixed_port.to_string()])
                 .output()?;
-                
+
             // 设置SOCKS代理
             let _ = Command::new("networksetup")
-                .args(&["-setsocksfirewallproxy", "Wi-Fi", "127.0.0.1", &CLASH_SOCKS_PORT.to_string()])
+                .args(&["-setsocksfirewallproxy", "Wi-Fi", "127.0.0.1", &runtime_config.socks_port.to_string()])
                 .output()?;
                 
             // 启用代理
@@ -220,6 +653,13 @@ fn set_system_proxy(enable: bool) -> Result<()> {
             let _ = Command::new("networksetup")
                 .args(&["-setsocksfirewallproxystate", "Wi-Fi", "on"])
                 .output()?;
+
+            // 设置直连例外列表，避免LAN/回环以及用户自定义的域名也走代理
+            let bypass_domains = expanded_bypass_list();
+            let _ = Command::new("networksetup")
+                .args(["-setproxybypassdomains", "Wi-Fi"])
+                .args(&bypass_domains)
+                .output()?;
         } else {
             // 关闭代理
             let _ = Command::new("networksetup")
@@ -234,24 +674,11 @@ fn set_system_proxy(enable: bool) -> Result<()> {
         }
     }
     
-    // 为Windows添加代理设置逻辑
+    // 为Windows添加代理设置逻辑：通过WinInet按连接写入，reg add无法通知正在运行的程序
     #[cfg(target_os = "windows")]
     {
-        if enable {
-            // 设置Windows系统代理
-            let proxy_server = format!("127.0.0.1:{}", CLASH_PROXY_PORT);
-            let _ = Command::new("reg")
-                .args(&["add", "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings", "/v", "ProxyEnable", "/t", "REG_DWORD", "/d", "1", "/f"])
-                .output()?;
-            let _ = Command::new("reg")
-                .args(&["add", "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings", "/v", "ProxyServer", "/t", "REG_SZ", "/d", &proxy_server, "/f"])
-                .output()?;
-        } else {
-            // 关闭Windows系统代理
-            let _ = Command::new("reg")
-                .args(&["add", "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings", "/v", "ProxyEnable", "/t", "REG_DWORD", "/d", "0", "/f"])
-                .output()?;
-        }
+        let bypass = expanded_bypass_list().join(";") + ";<local>";
+        win_proxy::set_system_proxy(enable, &runtime_config, &bypass)?;
     }
     
     // 为Linux添加代理设置逻辑
@@ -267,19 +694,25 @@ fn set_system_proxy(enable: bool) -> Result<()> {
                 .args(&["set", "org.gnome.system.proxy.http", "host", "127.0.0.1"])
                 .output();
             let _ = Command::new("gsettings")
-                .args(&["set", "org.gnome.system.proxy.http", "port", &CLASH_PROXY_PORT.to_string()])
+                .args(&["set", "org.gnome.system.proxy.http", "port", &runtime_config.mixed_port.to_string()])
                 .output();
             let _ = Command::new("gsettings")
                 .args(&["set", "org.gnome.system.proxy.https", "host", "127.0.0.1"])
                 .output();
             let _ = Command::new("gsettings")
-                .args(&["set", "org.gnome.system.proxy.https", "port", &CLASH_PROXY_PORT.to_string()])
+                .args(&["set", "org.gnome.system.proxy.https", "port", &runtime_config.mixed_port.to_string()])
                 .output();
             let _ = Command::new("gsettings")
                 .args(&["set", "org.gnome.system.proxy.socks", "host", "127.0.0.1"])
                 .output();
             let _ = Command::new("gsettings")
-                .args(&["set", "org.gnome.system.proxy.socks", "port", &CLASH_SOCKS_PORT.to_string()])
+                .args(&["set", "org.gnome.system.proxy.socks", "port", &runtime_config.socks_port.to_string()])
+                .output();
+
+            // 设置直连例外列表
+            let ignore_hosts = gsettings_ignore_hosts_literal();
+            let _ = Command::new("gsettings")
+                .args(&["set", "org.gnome.system.proxy", "ignore-hosts", &ignore_hosts])
                 .output();
         } else {
             // 关闭GNOME代理
@@ -295,22 +728,31 @@ fn set_system_proxy(enable: bool) -> Result<()> {
 
 // 通过Clash API切换代理模式
 pub async fn set_mode(mode: ClashMode) -> Result<()> {
-    
+
     let client = Client::new();
+    let runtime_config = get_runtime_config();
     let mode_str = match mode {
         ClashMode::Rule => "rule",
         ClashMode::Global => "global",
         ClashMode::Direct => "direct",
     };
-    
-    let response = client.patch(format!("http://127.0.0.1:{}/configs", CLASH_API_PORT))
+
+    let response = client.patch(format!("{}/configs", runtime_config.controller_base_url()))
+        .bearer_auth(&runtime_config.secret)
         .json(&serde_json::json!({
             "mode": mode_str
         }))
         .send()
         .await;
-    
+
     match response {
+        Ok(res) if res.status() == reqwest::StatusCode::UNAUTHORIZED => {
+            eprintln!("设置Clash模式失败: Clash API secret校验未通过(401)");
+            Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "设置Clash模式失败: secret配置错误",
+            ))
+        },
         Ok(_) => {
             println!("Clash模式已设置为: {}", mode_str);
             Ok(())
@@ -325,14 +767,64 @@ pub async fn set_mode(mode: ClashMode) -> Result<()> {
     }
 }
 
+// 开启/关闭TUN（透明代理）模式。开启后Clash在网络层接管流量，连忽略系统代理设置的应用也能被代理。
+pub async fn set_tun_mode(enable: bool, stack: TunStack, dns_hijack: Vec<String>) -> Result<()> {
+    let client = Client::new();
+    let runtime_config = get_runtime_config();
+
+    let response = client.patch(format!("{}/configs", runtime_config.controller_base_url()))
+        .bearer_auth(&runtime_config.secret)
+        .json(&serde_json::json!({
+            "tun": {
+                "enable": enable,
+                "stack": stack.as_str(),
+                "auto-route": true,
+                "dns-hijack": dns_hijack,
+            }
+        }))
+        .send()
+        .await;
+
+    match response {
+        Ok(res) if res.status() == reqwest::StatusCode::UNAUTHORIZED => {
+            eprintln!("设置TUN模式失败: Clash API secret校验未通过(401)");
+            Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "设置TUN模式失败: secret配置错误",
+            ))
+        },
+        Ok(_) => {
+            TUN_ACTIVE.store(enable, Ordering::SeqCst);
+            println!("TUN模式已{}", if enable { "开启" } else { "关闭" });
+            Ok(())
+        },
+        Err(e) => {
+            eprintln!("设置TUN模式失败: {}", e);
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("设置TUN模式失败: {}", e),
+            ))
+        }
+    }
+}
+
 // 获取Clash当前状态
 pub async fn get_status() -> Result<serde_json::Value> {
     let client = Client::new();
-    let response = client.get(format!("http://127.0.0.1:{}/configs", CLASH_API_PORT))
+    let runtime_config = get_runtime_config();
+    let response = client.get(format!("{}/configs", runtime_config.controller_base_url()))
+        .bearer_auth(&runtime_config.secret)
         .send()
         .await;
-    
+
     match response {
+        Ok(res) if res.status() == reqwest::StatusCode::UNAUTHORIZED => {
+            eprintln!("获取Clash状态失败: Clash API secret校验未通过(401)");
+            Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "获取Clash状态失败: secret配置错误",
+            ))
+        },
         Ok(res) => {
             match res.json::<serde_json::Value>().await {
                 Ok(json) => Ok(json),
@@ -353,10 +845,10 @@ pub async fn get_status() -> Result<serde_json::Value> {
             ))
         }
     }
-} 
+}
 
 // 修改代理检查函数，返回错误码而不是布尔值
-pub fn check_system_proxy() -> std::result::Result<crate::common::ProxyCheckCode, std::io::Error> {
+pub async fn check_system_proxy() -> std::result::Result<crate::common::ProxyCheckCode, std::io::Error> {
     println!("检查系统代理状态...");
 
     // 直接调用 check_clash_process，不通过 clash 命名空间
@@ -366,91 +858,120 @@ pub fn check_system_proxy() -> std::result::Result<crate::common::ProxyCheckCode
         return Ok(crate::common::ProxyCheckCode::ClashProcessNotRunning);
     }
 
+    // TUN模式是在网络层接管流量的健康状态，不应被当成"系统代理未启用"
+    if let Ok(status) = get_status().await {
+        let tun_enabled = status.get("tun").and_then(|t| t.get("enable")).and_then(|v| v.as_bool()).unwrap_or(false);
+        TUN_ACTIVE.store(tun_enabled, Ordering::SeqCst);
+        if tun_enabled {
+            println!("TUN模式已开启，视为代理健康");
+            return Ok(crate::common::ProxyCheckCode::Ok);
+        }
+    }
+
+    let runtime_config = get_runtime_config();
+
     #[cfg(target_os = "macos")]
     {
         // 获取HTTP代理状态
         let output = Command::new("networksetup")
             .args(&["-getwebproxy", "Wi-Fi"])
             .output()?;
-        
+
         let output_str = String::from_utf8_lossy(&output.stdout);
-        
+
         // 检查代理是否启用
         let enabled = output_str.contains("Enabled: Yes");
         if !enabled {
             return Ok(crate::common::ProxyCheckCode::ProxyNotEnabled);
         }
-        
+
         // 检查代理服务器和端口
-        let correct_server = output_str.contains("Server: 127.0.0.1") && 
-                             output_str.contains(&format!("Port: {}", CLASH_PROXY_PORT));
-        
+        let correct_server = output_str.contains("Server: 127.0.0.1") &&
+                             output_str.contains(&format!("Port: {}", runtime_config.mixed_port));
+
         if !correct_server {
             return Ok(crate::common::ProxyCheckCode::ProxyServerIncorrect);
         }
-        
+
+        // 检查直连例外列表是否和预期一致
+        let bypass_output = Command::new("networksetup")
+            .args(&["-getproxybypassdomains", "Wi-Fi"])
+            .output()?;
+        let bypass_str = String::from_utf8_lossy(&bypass_output.stdout);
+        let current_bypass: std::collections::HashSet<&str> = bypass_str.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).collect();
+        let expected_bypass: Vec<String> = expanded_bypass_list();
+        if !expected_bypass.iter().all(|b| current_bypass.contains(b.as_str())) {
+            return Ok(crate::common::ProxyCheckCode::BypassListMismatch);
+        }
+
         return Ok(crate::common::ProxyCheckCode::Ok);
     }
-    
+
     #[cfg(target_os = "windows")]
     {
-        // 获取Windows系统代理设置
-        let reg_query = Command::new("reg")
-            .args(&["query", "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings", "/v", "ProxyEnable", "/v", "ProxyServer"])
-            .output()?;
-        
-        let output_str = String::from_utf8_lossy(&reg_query.stdout);
-        
-        // 检查代理是否启用
-        let enabled = output_str.contains("ProxyEnable    REG_DWORD    0x1");
-        if !enabled {
+        // 通过WinInet读回当前的per-connection代理设置
+        let current = win_proxy::query_system_proxy()?;
+        if !current.enabled {
             return Ok(crate::common::ProxyCheckCode::ProxyNotEnabled);
         }
-        
-        // 检查代理服务器和端口
-        let correct_server = output_str.contains(&format!("ProxyServer    REG_SZ    127.0.0.1:{}", CLASH_PROXY_PORT));
-        if !correct_server {
+
+        let expected_server = format!("127.0.0.1:{}", runtime_config.mixed_port);
+        if current.proxy_server != expected_server {
             return Ok(crate::common::ProxyCheckCode::ProxyServerIncorrect);
         }
-        
+
+        let expected_bypass = expanded_bypass_list().join(";") + ";<local>";
+        if current.bypass != expected_bypass {
+            return Ok(crate::common::ProxyCheckCode::BypassListMismatch);
+        }
+
         return Ok(crate::common::ProxyCheckCode::Ok);
     }
-    
+
     #[cfg(target_os = "linux")]
     {
         // 获取GNOME系统代理设置
         let proxy_mode = Command::new("gsettings")
             .args(&["get", "org.gnome.system.proxy", "mode"])
             .output()?;
-        
+
         let host = Command::new("gsettings")
             .args(&["get", "org.gnome.system.proxy.http", "host"])
             .output()?;
-        
+
         let port = Command::new("gsettings")
             .args(&["get", "org.gnome.system.proxy.http", "port"])
             .output()?;
-        
+
         let proxy_mode_str = String::from_utf8_lossy(&proxy_mode.stdout);
         let host_str = String::from_utf8_lossy(&host.stdout);
         let port_str = String::from_utf8_lossy(&port.stdout);
-        
+
         // 检查代理是否启用
         let enabled = proxy_mode_str.trim() == "'manual'";
         if !enabled {
             return Ok(crate::common::ProxyCheckCode::ProxyNotEnabled);
         }
-        
+
         // 检查代理服务器和端口是否正确
-        let correct_server = host_str.trim() == "'127.0.0.1'" && 
-                             port_str.trim() == &CLASH_PROXY_PORT.to_string();
+        let correct_server = host_str.trim() == "'127.0.0.1'" &&
+                             port_str.trim() == &runtime_config.mixed_port.to_string();
         if !correct_server {
             return Ok(crate::common::ProxyCheckCode::ProxyServerIncorrect);
         }
-        
+
+        // 检查直连例外列表是否和预期一致
+        let ignore_hosts = Command::new("gsettings")
+            .args(&["get", "org.gnome.system.proxy", "ignore-hosts"])
+            .output()?;
+        let ignore_hosts_str = String::from_utf8_lossy(&ignore_hosts.stdout).trim().to_string();
+        if ignore_hosts_str != gsettings_ignore_hosts_literal() {
+            return Ok(crate::common::ProxyCheckCode::BypassListMismatch);
+        }
+
         return Ok(crate::common::ProxyCheckCode::Ok);
     }
-    
+
     // 默认情况下假设代理配置正确
     #[allow(unreachable_code)]
     Ok(crate::common::ProxyCheckCode::Ok)
@@ -487,4 +1008,191 @@ pub fn check_clash_process() -> bool {
         println!("没有找到正在运行的 Clash 进程");
         false
     }
+}
+
+// Windows下通过WinInet的per-connection API设置/查询系统代理，取代reg add/query。
+// reg方式不会通知已经在运行的程序，也无法表达bypass例外列表，这里改为逐连接下发设置。
+#[cfg(target_os = "windows")]
+mod win_proxy {
+    use super::ClashRuntimeConfig;
+    use std::io::{Error, ErrorKind, Result};
+    use std::mem::size_of;
+    use std::ptr::null_mut;
+    use windows_sys::Win32::Networking::WinInet::{
+        InternetQueryOptionW, InternetSetOptionW, INTERNET_OPTION_PER_CONNECTION_OPTION,
+        INTERNET_OPTION_PROXY_SETTINGS_CHANGED, INTERNET_OPTION_REFRESH, INTERNET_PER_CONN_FLAGS,
+        INTERNET_PER_CONN_OPTIONW, INTERNET_PER_CONN_OPTIONW_0, INTERNET_PER_CONN_OPTION_LISTW,
+        INTERNET_PER_CONN_PROXY_BYPASS, INTERNET_PER_CONN_PROXY_SERVER, PROXY_TYPE_DIRECT,
+        PROXY_TYPE_PROXY,
+    };
+    use windows_sys::Win32::NetworkManagement::Ras::{RasEnumEntriesW, RASENTRYNAMEW};
+    use windows_sys::Win32::System::Memory::GlobalFree;
+
+    pub struct CurrentProxyState {
+        pub enabled: bool,
+        pub proxy_server: String,
+        pub bypass: String,
+    }
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    fn from_wide(ptr: *mut u16) -> String {
+        if ptr.is_null() {
+            return String::new();
+        }
+        let mut len = 0usize;
+        unsafe {
+            while *ptr.add(len) != 0 {
+                len += 1;
+            }
+            String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len))
+        }
+    }
+
+    // 枚举所有RAS(拨号/VPN)连接名，这样代理设置不只作用于默认LAN连接
+    fn enumerate_ras_connections() -> Vec<String> {
+        let mut count: u32 = 0;
+        let mut size: u32 = size_of::<RASENTRYNAMEW>() as u32;
+        let mut entries: Vec<RASENTRYNAMEW> = vec![unsafe { std::mem::zeroed() }];
+        entries[0].dwSize = size_of::<RASENTRYNAMEW>() as u32;
+
+        let ret = unsafe { RasEnumEntriesW(null_mut(), null_mut(), entries.as_mut_ptr(), &mut size, &mut count) };
+        const ERROR_INSUFFICIENT_BUFFER: i32 = 122;
+        if ret == ERROR_INSUFFICIENT_BUFFER {
+            let entry_count = ((size as usize) / size_of::<RASENTRYNAMEW>()).max(1);
+            entries = vec![unsafe { std::mem::zeroed() }; entry_count];
+            for e in entries.iter_mut() {
+                e.dwSize = size_of::<RASENTRYNAMEW>() as u32;
+            }
+            if unsafe { RasEnumEntriesW(null_mut(), null_mut(), entries.as_mut_ptr(), &mut size, &mut count) } != 0 {
+                return Vec::new();
+            }
+        } else if ret != 0 {
+            return Vec::new();
+        }
+
+        entries
+            .into_iter()
+            .take(count as usize)
+            .map(|e| {
+                let len = e.szEntryName.iter().position(|&c| c == 0).unwrap_or(e.szEntryName.len());
+                String::from_utf16_lossy(&e.szEntryName[..len])
+            })
+            .collect()
+    }
+
+    // 为单个连接（None表示默认LAN连接）写入代理设置
+    fn apply_for_connection(connection: Option<&str>, enable: bool, proxy_server: &str, bypass: &str) -> Result<()> {
+        let mut connection_w = connection.map(wide);
+        let connection_ptr = connection_w.as_mut().map_or(null_mut(), |v| v.as_mut_ptr());
+
+        let flags: u32 = if enable { PROXY_TYPE_PROXY | PROXY_TYPE_DIRECT } else { PROXY_TYPE_DIRECT };
+        let mut proxy_server_w = wide(proxy_server);
+        let mut bypass_w = wide(bypass);
+
+        let mut options = [
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_FLAGS, Value: INTERNET_PER_CONN_OPTIONW_0 { dwValue: flags } },
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_PROXY_SERVER, Value: INTERNET_PER_CONN_OPTIONW_0 { pszValue: proxy_server_w.as_mut_ptr() } },
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_PROXY_BYPASS, Value: INTERNET_PER_CONN_OPTIONW_0 { pszValue: bypass_w.as_mut_ptr() } },
+        ];
+
+        let mut option_list = INTERNET_PER_CONN_OPTION_LISTW {
+            dwSize: size_of::<INTERNET_PER_CONN_OPTION_LISTW>() as u32,
+            pszConnection: connection_ptr,
+            dwOptionCount: options.len() as u32,
+            dwOptionError: 0,
+            pOptions: options.as_mut_ptr(),
+        };
+
+        let ok = unsafe {
+            InternetSetOptionW(
+                0,
+                INTERNET_OPTION_PER_CONNECTION_OPTION,
+                &mut option_list as *mut _ as *mut core::ffi::c_void,
+                size_of::<INTERNET_PER_CONN_OPTION_LISTW>() as u32,
+            )
+        };
+
+        if ok == 0 {
+            return Err(Error::new(ErrorKind::Other, "InternetSetOptionW设置per-connection代理失败"));
+        }
+
+        Ok(())
+    }
+
+    pub fn set_system_proxy(enable: bool, runtime_config: &ClashRuntimeConfig, bypass: &str) -> Result<()> {
+        let proxy_server = format!("127.0.0.1:{}", runtime_config.mixed_port);
+
+        // 默认LAN连接
+        apply_for_connection(None, enable, &proxy_server, bypass)?;
+
+        // 以及每一个拨号/VPN连接，避免它们绕过系统代理
+        for name in enumerate_ras_connections() {
+            if let Err(e) = apply_for_connection(Some(&name), enable, &proxy_server, bypass) {
+                println!("为RAS连接[{}]设置代理失败: {:?}", name, e);
+            }
+        }
+
+        // 通知已运行的程序（如浏览器）立即生效，无需重启
+        unsafe {
+            InternetSetOptionW(0, INTERNET_OPTION_PROXY_SETTINGS_CHANGED, null_mut(), 0);
+            InternetSetOptionW(0, INTERNET_OPTION_REFRESH, null_mut(), 0);
+        }
+
+        Ok(())
+    }
+
+    pub fn query_system_proxy() -> Result<CurrentProxyState> {
+        let mut options = [
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_FLAGS, Value: INTERNET_PER_CONN_OPTIONW_0 { dwValue: 0 } },
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_PROXY_SERVER, Value: INTERNET_PER_CONN_OPTIONW_0 { pszValue: null_mut() } },
+            INTERNET_PER_CONN_OPTIONW { dwOption: INTERNET_PER_CONN_PROXY_BYPASS, Value: INTERNET_PER_CONN_OPTIONW_0 { pszValue: null_mut() } },
+        ];
+
+        let mut option_list = INTERNET_PER_CONN_OPTION_LISTW {
+            dwSize: size_of::<INTERNET_PER_CONN_OPTION_LISTW>() as u32,
+            pszConnection: null_mut(),
+            dwOptionCount: options.len() as u32,
+            dwOptionError: 0,
+            pOptions: options.as_mut_ptr(),
+        };
+
+        let mut size = size_of::<INTERNET_PER_CONN_OPTION_LISTW>() as u32;
+        let ok = unsafe {
+            InternetQueryOptionW(
+                0,
+                INTERNET_OPTION_PER_CONNECTION_OPTION,
+                &mut option_list as *mut _ as *mut core::ffi::c_void,
+                &mut size,
+            )
+        };
+
+        if ok == 0 {
+            return Err(Error::new(ErrorKind::Other, "InternetQueryOptionW读取per-connection代理失败"));
+        }
+
+        let flags = unsafe { options[0].Value.dwValue };
+        let proxy_server_ptr = unsafe { options[1].Value.pszValue };
+        let bypass_ptr = unsafe { options[2].Value.pszValue };
+        let proxy_server = from_wide(proxy_server_ptr);
+        let bypass = from_wide(bypass_ptr);
+
+        // InternetQueryOptionW为pszValue分配的缓冲区由调用方负责用GlobalFree释放，否则每次查询都会泄漏
+        unsafe {
+            if !proxy_server_ptr.is_null() {
+                GlobalFree(proxy_server_ptr as _);
+            }
+            if !bypass_ptr.is_null() {
+                GlobalFree(bypass_ptr as _);
+            }
+        }
+
+        Ok(CurrentProxyState {
+            enabled: flags & PROXY_TYPE_PROXY != 0,
+            proxy_server,
+            bypass,
+        })
+    }
 }
\ No newline at end of file
@@ -2,7 +2,7 @@
 
 // 导入必要的模块
 use tauri::{AppHandle, Wry};
-use crate::clash::{self, ClashMode};
+use crate::clash::{self, ClashMode, TunStack};
 use crate::common::{ProxyCheckCode, AccountStatus};
 use crate::account;
 /// 启动Clash并设置系统代理
@@ -47,7 +47,14 @@ pub async fn disconnect_vpn() -> Result<(), String> {
 #[tauri::command]
 pub async fn get_clash_status() -> Result<serde_json::Value, String> {
     clash::get_status().await.map_err(|e| e.to_string())
-} 
+}
+
+/// 开启/关闭TUN（透明代理）模式
+#[tauri::command]
+pub async fn set_tun_mode(enable: bool, gvisor: bool, dns_hijack: Vec<String>) -> Result<(), String> {
+    let stack = if gvisor { TunStack::Gvisor } else { TunStack::System };
+    clash::set_tun_mode(enable, stack, dns_hijack).await.map_err(|e| e.to_string())
+}
 
 #[tauri::command]
 pub fn log_to_console(message: String) {
@@ -56,7 +63,7 @@ pub fn log_to_console(message: String) {
 #[tauri::command]
 pub async fn check_system_proxy() -> Result<ProxyCheckCode, String> {
     // 调用修改后的检查函数
-    match clash::check_system_proxy() {
+    match clash::check_system_proxy().await {
         Ok(code) => {
             println!("系统代理检查结果: {:?} - {}", code, code.get_message());
             Ok(code)
@@ -7,6 +7,7 @@ pub enum ProxyCheckCode {
     ProxyNotEnabled = 2,
     ProxyServerIncorrect = 3,
     CheckError = 4,
+    BypassListMismatch = 5,
 }
 
 impl ProxyCheckCode {
@@ -17,6 +18,7 @@ impl ProxyCheckCode {
             Self::ProxyNotEnabled => "系统代理未启用，请重新连接",
             Self::ProxyServerIncorrect => "系统代理配置错误，请重新连接",
             Self::CheckError => "系统代理检查失败，请检查网络连接",
+            Self::BypassListMismatch => "代理直连例外列表与预期不符，请重新连接",
         }
     }
 }
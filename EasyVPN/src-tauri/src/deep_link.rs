@@ -0,0 +1,75 @@
+// 深度链接：处理easyvpn://import和clash://install-config，导入远程订阅并重启内核
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use url::Url;
+
+// 处理一批传入的链接（来自deep-link事件，或单实例转发过来的启动参数），忽略无法识别的链接
+pub fn handle_incoming_urls(app_handle: &AppHandle<Wry>, urls: &[String]) {
+    for raw_url in urls {
+        if let Some(subscription_url) = extract_subscription_url(raw_url) {
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = import_subscription(&app_handle, &subscription_url).await {
+                    eprintln!("导入订阅失败: {}", e);
+                    notify_import_result(&app_handle, false, &e);
+                }
+            });
+        }
+    }
+}
+
+// 解析 easyvpn://import?url=... 和 clash://install-config?url=... 两种深度链接，取出真正的订阅地址
+fn extract_subscription_url(raw_url: &str) -> Option<String> {
+    let parsed = Url::parse(raw_url).ok()?;
+
+    let is_import_link = matches!(
+        (parsed.scheme(), parsed.host_str()),
+        ("easyvpn", Some("import")) | ("clash", Some("install-config"))
+    );
+
+    if !is_import_link {
+        return None;
+    }
+
+    parsed
+        .query_pairs()
+        .find(|(key, _)| key == "url")
+        .map(|(_, value)| value.into_owned())
+}
+
+// 下载订阅YAML，通过clash模块校验/合并并持久化为当前配置，再重启内核使其生效
+async fn import_subscription(app_handle: &AppHandle<Wry>, subscription_url: &str) -> Result<(), String> {
+    println!("开始导入订阅: {}", subscription_url);
+
+    let response = reqwest::get(subscription_url)
+        .await
+        .map_err(|e| format!("下载订阅失败: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("下载订阅失败，HTTP状态码: {}", response.status()));
+    }
+
+    let yaml_content = response.text().await.map_err(|e| format!("读取订阅内容失败: {}", e))?;
+
+    let config_path = crate::clash::import_subscription_config(app_handle, &yaml_content)
+        .map_err(|e| format!("保存订阅配置失败: {}", e))?;
+    println!("订阅配置已保存到: {:?}", config_path);
+
+    crate::commands::stop_clash(app_handle.clone())?;
+    crate::commands::start_clash(app_handle.clone())?;
+
+    println!("订阅导入完成，Clash已使用新配置重启");
+    notify_import_result(app_handle, true, "订阅导入成功");
+    Ok(())
+}
+
+fn notify_import_result(app_handle: &AppHandle<Wry>, success: bool, message: &str) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let payload = serde_json::json!({
+            "success": success,
+            "message": message,
+        });
+        if let Err(e) = window.emit("subscription-imported", payload) {
+            eprintln!("推送subscription-imported事件失败: {}", e);
+        }
+    }
+}
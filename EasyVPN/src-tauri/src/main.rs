@@ -6,6 +6,11 @@ mod commands;
 mod clash;
 mod common;
 mod account;
+mod tray;
+mod remote_control;
+mod startup;
+mod updater;
+mod deep_link;
 use tauri::{AppHandle, Manager};
 use tokio::join;
 
@@ -15,7 +20,26 @@ fn main() {
     let tauri_builder = tauri::Builder::default();
     
     tauri_builder
+        // 单实例守卫必须第一个注册，确保第二次启动在走到.setup()之前就被拦截
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            println!("检测到应用已在运行，聚焦已有窗口，转发本次启动参数: {:?}", argv);
+            if let Some(main_window) = app.get_webview_window("main") {
+                if let Err(e) = main_window.show() {
+                    eprintln!("聚焦已有窗口时显示失败: {}", e);
+                }
+                if let Err(e) = main_window.set_focus() {
+                    eprintln!("聚焦已有窗口失败: {}", e);
+                }
+            }
+            // Windows/Linux下深度链接是以启动参数的形式转发过来的，交给deep_link模块识别处理
+            deep_link::handle_incoming_urls(app, &argv);
+        }))
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            None,
+        ))
+        .plugin(tauri_plugin_deep_link::init())
         .setup(|app| {
             
             let app_handle = app.handle();
@@ -27,17 +51,44 @@ fn main() {
                 let app_handle_clone = app_handle.clone();
                 main_window.on_window_event(move |event| {
                     match event {
-                        tauri::WindowEvent::CloseRequested { .. } => {
-                            println!("接收到窗口关闭请求");
-                            // 在窗口关闭时停止Clash并关闭系统代理
-                            if let Err(e) = commands::stop_clash(app_handle_clone.clone()) {
-                                eprintln!("关闭Clash时出错: {}", e);
+                        tauri::WindowEvent::CloseRequested { api, .. } => {
+                            println!("接收到窗口关闭请求，隐藏到系统托盘");
+                            // 关闭按钮只隐藏窗口，Clash继续运行，只有托盘的"退出"才会真正停止
+                            api.prevent_close();
+                            startup::save_window_geometry(&app_handle_clone);
+                            if let Some(window) = app_handle_clone.get_webview_window("main") {
+                                if let Err(e) = window.hide() {
+                                    eprintln!("隐藏主窗口失败: {}", e);
+                                }
                             }
                         }
                         _ => {}
                     }
                 });
-            
+
+            }
+
+            // 初始化系统托盘
+            if let Err(e) = tray::setup_tray(&app_handle) {
+                eprintln!("初始化系统托盘失败: {}", e);
+            }
+
+            // 注册easyvpn://和clash://深度链接，macOS/Windows生产环境由打包配置里的URL scheme触发
+            #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                if let Err(e) = app.deep_link().register_all() {
+                    eprintln!("注册深度链接scheme失败: {}", e);
+                }
+            }
+
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                let deep_link_handle = app_handle.clone();
+                app.deep_link().on_open_url(move |event| {
+                    let urls: Vec<String> = event.urls().iter().map(|u| u.to_string()).collect();
+                    deep_link::handle_incoming_urls(&deep_link_handle, &urls);
+                });
             }
             
             // 在后台执行初始化流程，完成后再显示窗口
@@ -72,11 +123,27 @@ fn main() {
                     }
                 });
                 
+                // 按配置启动局域网控制面板（默认关闭）
+                remote_control::start_remote_control_server(app_handle_clone.clone());
+
                 // 等待两个任务完成
                 let (_clash_result, _account_result) = join!(clash_task, account_task);
-                
+
+                // 初始化完成后刷新托盘状态，反映真实的连接情况
+                tray::refresh_tray_state(&app_handle_clone).await;
+
+                // 账号初始化完成后，后台自动检查一次应用和Clash内核的更新，不阻塞窗口显示
+                let update_check_handle = app_handle_clone.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = updater::run_update_check(&update_check_handle).await {
+                        eprintln!("自动检查更新失败: {}", e);
+                    }
+                });
+
                 // 初始化完成后显示窗口
                 println!("后端初始化完成，准备显示窗口...");
+                // 显示前先恢复上次记录的窗口大小和位置，而不是用默认坐标
+                startup::restore_window_geometry(&app_handle_clone);
                 if let Some(main_window) = app_handle_clone.get_webview_window("main") {
                     // 直接显示窗口，前端通过监听visibilitychange事件知道窗口已显示
                     if let Err(e) = main_window.show() {
@@ -96,6 +163,7 @@ fn main() {
             commands::connect_vpn,
             commands::disconnect_vpn,
             commands::get_clash_status,
+            commands::set_tun_mode,
             commands::log_to_console,
             commands::check_system_proxy,
             commands::get_account_info,
@@ -105,7 +173,12 @@ fn main() {
             account::login,
             account::logout,
             account::get_current_device_info,
+            account::get_device_public_key,
+            account::list_devices,
             account::update_and_get_account,
+            startup::set_auto_launch,
+            startup::get_auto_launch,
+            updater::check_for_updates,
         ])
         .run(tauri::generate_context!())
         .expect("应用程序运行失败");
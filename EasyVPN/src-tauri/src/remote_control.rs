@@ -0,0 +1,137 @@
+// 局域网控制面板：允许同一局域网内的其它设备查询/控制本机VPN，默认关闭，需要用户在配置文件中显式开启
+use rocket::{serde::json::Json, State, routes, get, post};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands;
+
+const REMOTE_CONTROL_CONFIG_FILENAME: &str = "remoteControl.json";
+
+// 局域网控制面板的开关配置，默认关闭
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteControlConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+fn default_bind_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    34988
+}
+
+impl Default for RemoteControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: default_bind_address(),
+            port: default_port(),
+        }
+    }
+}
+
+fn get_config_file_path(app_handle: &AppHandle) -> Option<PathBuf> {
+    let app_data_dir = app_handle.path().app_data_dir().ok()?;
+    if !app_data_dir.exists() {
+        std::fs::create_dir_all(&app_data_dir).ok()?;
+    }
+    Some(app_data_dir.join(REMOTE_CONTROL_CONFIG_FILENAME))
+}
+
+// 读取控制面板配置，文件不存在或解析失败时回退为默认（关闭）配置
+fn load_config(app_handle: &AppHandle) -> RemoteControlConfig {
+    let Some(config_path) = get_config_file_path(app_handle) else {
+        return RemoteControlConfig::default();
+    };
+
+    match std::fs::read_to_string(&config_path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => RemoteControlConfig::default(),
+    }
+}
+
+// 路由函数无法捕获环境变量，只能通过Rocket的State读取共享的AppHandle
+struct ControlState {
+    app_handle: Arc<AppHandle>,
+}
+
+// 把局域网控制面板触发的操作推送给前端窗口，使UI能实时反映远程操作结果
+fn notify_remote_control(app_handle: &AppHandle, action: &str, success: bool) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let payload = serde_json::json!({
+            "action": action,
+            "success": success,
+        });
+        if let Err(e) = window.emit("remote-control", payload) {
+            eprintln!("推送remote-control事件失败: {}", e);
+        }
+    }
+}
+
+#[get("/status")]
+async fn handle_status(_state: &State<ControlState>) -> Json<serde_json::Value> {
+    let status = commands::get_clash_status().await.unwrap_or_else(|_| serde_json::json!({}));
+    Json(status)
+}
+
+#[post("/connect")]
+async fn handle_connect(state: &State<ControlState>) -> Json<serde_json::Value> {
+    let app_handle = (*state.app_handle).clone();
+    let result = commands::connect_vpn(app_handle.clone(), false).await;
+    let success = result.is_ok();
+    if let Err(e) = result {
+        eprintln!("局域网控制面板连接失败: {}", e);
+    }
+    notify_remote_control(&app_handle, "connect", success);
+    Json(serde_json::json!({ "success": success }))
+}
+
+#[post("/disconnect")]
+async fn handle_disconnect(state: &State<ControlState>) -> Json<serde_json::Value> {
+    let app_handle = (*state.app_handle).clone();
+    let result = commands::disconnect_vpn().await;
+    let success = result.is_ok();
+    if let Err(e) = result {
+        eprintln!("局域网控制面板断开失败: {}", e);
+    }
+    notify_remote_control(&app_handle, "disconnect", success);
+    Json(serde_json::json!({ "success": success }))
+}
+
+// 按配置启动局域网控制面板服务器，默认关闭，需要用户在配置文件中显式开启才会监听
+pub fn start_remote_control_server(app_handle: AppHandle) {
+    let config = load_config(&app_handle);
+
+    if !config.enabled {
+        println!("局域网控制面板未启用，跳过启动");
+        return;
+    }
+
+    println!("启动局域网控制面板 http://{}:{}", config.bind_address, config.port);
+
+    let state = ControlState {
+        app_handle: Arc::new(app_handle),
+    };
+
+    tauri::async_runtime::spawn(async move {
+        let figment = rocket::Config::figment()
+            .merge(("port", config.port))
+            .merge(("address", config.bind_address.as_str()));
+
+        let rocket_instance = rocket::custom(figment)
+            .mount("/", routes![handle_status, handle_connect, handle_disconnect])
+            .manage(state);
+
+        if let Err(e) = rocket_instance.launch().await {
+            eprintln!("局域网控制面板启动失败: {}", e);
+        }
+    });
+}
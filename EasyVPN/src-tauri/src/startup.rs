@@ -0,0 +1,97 @@
+// 开机自启动 + 窗口几何记忆：应用重启后恢复上次关闭时的窗口大小和位置
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, Wry};
+use tauri_plugin_autostart::ManagerExt;
+
+const WINDOW_GEOMETRY_FILENAME: &str = "windowGeometry.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+fn get_geometry_file_path(app_handle: &AppHandle<Wry>) -> Option<PathBuf> {
+    let app_data_dir = app_handle.path().app_data_dir().ok()?;
+    if !app_data_dir.exists() {
+        std::fs::create_dir_all(&app_data_dir).ok()?;
+    }
+    Some(app_data_dir.join(WINDOW_GEOMETRY_FILENAME))
+}
+
+// 在窗口关闭前调用，把当前大小和位置写入磁盘
+pub fn save_window_geometry(app_handle: &AppHandle<Wry>) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+    let Some(geometry_path) = get_geometry_file_path(app_handle) else {
+        return;
+    };
+
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.outer_size()) else {
+        return;
+    };
+
+    let geometry = WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+
+    match serde_json::to_string(&geometry) {
+        Ok(content) => {
+            if let Err(e) = std::fs::write(&geometry_path, content) {
+                eprintln!("保存窗口位置失败: {}", e);
+            }
+        }
+        Err(e) => eprintln!("序列化窗口位置失败: {}", e),
+    }
+}
+
+// 在.setup阶段、窗口显示前调用，恢复上次记录的大小和位置
+pub fn restore_window_geometry(app_handle: &AppHandle<Wry>) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+    let Some(geometry_path) = get_geometry_file_path(app_handle) else {
+        return;
+    };
+
+    let Ok(content) = std::fs::read_to_string(&geometry_path) else {
+        return;
+    };
+    let Ok(geometry) = serde_json::from_str::<WindowGeometry>(&content) else {
+        return;
+    };
+
+    if let Err(e) = window.set_size(PhysicalSize::new(geometry.width, geometry.height)) {
+        eprintln!("恢复窗口大小失败: {}", e);
+    }
+    if let Err(e) = window.set_position(PhysicalPosition::new(geometry.x, geometry.y)) {
+        eprintln!("恢复窗口位置失败: {}", e);
+    }
+}
+
+/// 开启/关闭开机自启动
+#[tauri::command]
+pub fn set_auto_launch(app_handle: AppHandle<Wry>, enabled: bool) -> Result<(), String> {
+    let autolaunch = app_handle.autolaunch();
+    if enabled {
+        autolaunch.enable().map_err(|e| format!("开启开机自启动失败: {}", e))
+    } else {
+        autolaunch.disable().map_err(|e| format!("关闭开机自启动失败: {}", e))
+    }
+}
+
+/// 查询当前是否已注册开机自启动
+#[tauri::command]
+pub fn get_auto_launch(app_handle: AppHandle<Wry>) -> Result<bool, String> {
+    app_handle
+        .autolaunch()
+        .is_enabled()
+        .map_err(|e| format!("查询开机自启动状态失败: {}", e))
+}
@@ -0,0 +1,114 @@
+// 系统托盘：提供连接/断开、显示隐藏窗口、退出的快捷入口，窗口关闭时只隐藏不退出
+use tauri::{AppHandle, Manager, Wry};
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use crate::commands;
+
+const MENU_ID_CONNECT: &str = "tray-connect";
+const MENU_ID_DISCONNECT: &str = "tray-disconnect";
+const MENU_ID_TOGGLE_WINDOW: &str = "tray-toggle-window";
+const MENU_ID_QUIT: &str = "tray-quit";
+
+// 构建托盘图标和右键菜单，并把TrayIcon句柄托管进AppHandle状态，供refresh_tray_state后续更新
+pub fn setup_tray(app_handle: &AppHandle<Wry>) -> tauri::Result<()> {
+    let connect_item = MenuItem::with_id(app_handle, MENU_ID_CONNECT, "连接", true, None::<&str>)?;
+    let disconnect_item = MenuItem::with_id(app_handle, MENU_ID_DISCONNECT, "断开", true, None::<&str>)?;
+    let toggle_window_item = MenuItem::with_id(app_handle, MENU_ID_TOGGLE_WINDOW, "显示/隐藏窗口", true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app_handle, MENU_ID_QUIT, "退出", true, None::<&str>)?;
+
+    let menu = Menu::with_items(
+        app_handle,
+        &[
+            &connect_item,
+            &disconnect_item,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &toggle_window_item,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &quit_item,
+        ],
+    )?;
+
+    let menu_event_handle = app_handle.clone();
+    let click_event_handle = app_handle.clone();
+
+    let mut tray_builder = TrayIconBuilder::new()
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .tooltip("EasyVPN");
+
+    if let Some(icon) = app_handle.default_window_icon() {
+        tray_builder = tray_builder.icon(icon.clone());
+    }
+
+    let tray = tray_builder
+        .on_menu_event(move |_tray, event| {
+            let app_handle = menu_event_handle.clone();
+            match event.id.as_ref() {
+                MENU_ID_CONNECT => {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = commands::connect_vpn(app_handle.clone(), false).await {
+                            eprintln!("托盘连接失败: {}", e);
+                        }
+                        refresh_tray_state(&app_handle).await;
+                    });
+                }
+                MENU_ID_DISCONNECT => {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = commands::disconnect_vpn().await {
+                            eprintln!("托盘断开失败: {}", e);
+                        }
+                        refresh_tray_state(&app_handle).await;
+                    });
+                }
+                MENU_ID_TOGGLE_WINDOW => toggle_main_window(&app_handle),
+                MENU_ID_QUIT => {
+                    // 只有显式点击"退出"才真正停止Clash并关闭系统代理
+                    if let Err(e) = commands::stop_clash(app_handle.clone()) {
+                        eprintln!("退出前停止Clash失败: {}", e);
+                    }
+                    app_handle.exit(0);
+                }
+                _ => {}
+            }
+        })
+        .on_tray_icon_event(move |_tray, event| {
+            if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                toggle_main_window(&click_event_handle);
+            }
+        })
+        .build(app_handle)?;
+
+    app_handle.manage(tray);
+
+    Ok(())
+}
+
+// 切换主窗口的显示/隐藏
+fn toggle_main_window(app_handle: &AppHandle<Wry>) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let is_visible = window.is_visible().unwrap_or(false);
+        if is_visible {
+            let _ = window.hide();
+        } else {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    }
+}
+
+// 根据当前Clash模式刷新托盘提示文案（Rule/Global视为已连接，Direct视为未连接）
+pub async fn refresh_tray_state(app_handle: &AppHandle<Wry>) {
+    let mode = commands::get_clash_status()
+        .await
+        .ok()
+        .and_then(|status| status.get("mode").and_then(|m| m.as_str()).map(|s| s.to_lowercase()));
+
+    let connected = matches!(mode.as_deref(), Some("rule") | Some("global"));
+    let tooltip = if connected { "EasyVPN（已连接）" } else { "EasyVPN（未连接）" };
+
+    if let Some(tray) = app_handle.try_state::<TrayIcon<Wry>>() {
+        if let Err(e) = tray.set_tooltip(Some(tooltip)) {
+            eprintln!("更新托盘提示失败: {}", e);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+// 应用本体与Clash内核的统一更新检查/下载/校验/替换流程
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+const RELEASE_MANIFEST_URL: &str = "https://update.easyvpn.app/manifest.json";
+const CLASH_VERSION_FILENAME: &str = "clashVersion.json";
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseManifest {
+    #[serde(rename = "appVersion")]
+    app_version: String,
+    #[serde(rename = "appDownloadUrl")]
+    app_download_url: String,
+    #[serde(rename = "appSha256")]
+    app_sha256: String,
+    #[serde(rename = "clashVersion")]
+    clash_version: String,
+    #[serde(rename = "clashDownloadUrl")]
+    clash_download_url: String,
+    #[serde(rename = "clashSha256")]
+    clash_sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UpdateProgress {
+    target: &'static str,
+    stage: &'static str,
+    message: String,
+}
+
+// 检查结果返回给前端，驱动下载/重启提示
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateCheckResult {
+    pub app_version: String,
+    pub latest_app_version: String,
+    pub app_update_staged_path: Option<String>,
+    pub clash_version_updated_to: Option<String>,
+}
+
+fn emit_progress(app_handle: &AppHandle<Wry>, target: &'static str, stage: &'static str, message: impl Into<String>) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let payload = UpdateProgress {
+            target,
+            stage,
+            message: message.into(),
+        };
+        if let Err(e) = window.emit("update-progress", payload) {
+            eprintln!("推送update-progress事件失败: {}", e);
+        }
+    }
+}
+
+async fn fetch_manifest() -> Result<ReleaseManifest, String> {
+    let client = Client::new();
+    let response = client
+        .get(RELEASE_MANIFEST_URL)
+        .send()
+        .await
+        .map_err(|e| format!("获取更新清单失败: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("获取更新清单失败，HTTP状态码: {}", response.status()));
+    }
+
+    response
+        .json::<ReleaseManifest>()
+        .await
+        .map_err(|e| format!("解析更新清单失败: {}", e))
+}
+
+// 下载更新包并校验SHA256，校验不一致时不落盘，避免写入损坏或被篡改的内容
+async fn download_and_verify(url: &str, expected_sha256: &str) -> Result<Vec<u8>, String> {
+    let client = Client::new();
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("下载更新包失败: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("下载更新包失败，HTTP状态码: {}", response.status()));
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("读取更新包内容失败: {}", e))?
+        .to_vec();
+
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    let digest = format!("{:x}", hasher.finalize());
+
+    if !digest.eq_ignore_ascii_case(expected_sha256) {
+        return Err(format!("更新包校验和不匹配，期望{}，实际{}", expected_sha256, digest));
+    }
+
+    Ok(bytes)
+}
+
+fn clash_bin_path(app_handle: &AppHandle<Wry>) -> Result<PathBuf, String> {
+    let resource_path = app_handle
+        .path()
+        .resource_dir()
+        .map_err(|e| format!("无法获取资源路径: {}", e))?;
+
+    let bin_dir = if resource_path.to_string_lossy().contains("resources") {
+        resource_path.join("bin")
+    } else {
+        resource_path.join("resources").join("bin")
+    };
+
+    Ok(bin_dir.join("clash-darwin-arm64"))
+}
+
+fn get_clash_version_file_path(app_handle: &AppHandle<Wry>) -> Option<PathBuf> {
+    let app_data_dir = app_handle.path().app_data_dir().ok()?;
+    if !app_data_dir.exists() {
+        std::fs::create_dir_all(&app_data_dir).ok()?;
+    }
+    Some(app_data_dir.join(CLASH_VERSION_FILENAME))
+}
+
+fn read_installed_clash_version(app_handle: &AppHandle<Wry>) -> Option<String> {
+    let path = get_clash_version_file_path(app_handle)?;
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_installed_clash_version(app_handle: &AppHandle<Wry>, version: &str) {
+    let Some(path) = get_clash_version_file_path(app_handle) else {
+        return;
+    };
+    match serde_json::to_string(version) {
+        Ok(content) => {
+            if let Err(e) = std::fs::write(path, content) {
+                eprintln!("记录Clash内核版本失败: {}", e);
+            }
+        }
+        Err(e) => eprintln!("序列化Clash内核版本失败: {}", e),
+    }
+}
+
+// 应用本体的更新目前只负责下载校验安装包并暂存，具体替换交给平台安装程序完成
+async fn stage_app_update(app_handle: &AppHandle<Wry>, manifest: &ReleaseManifest) -> Result<PathBuf, String> {
+    emit_progress(app_handle, "app", "downloading", "正在下载新版本安装包...");
+    let bytes = download_and_verify(&manifest.app_download_url, &manifest.app_sha256).await?;
+
+    emit_progress(app_handle, "app", "verifying", "校验通过，正在保存安装包...");
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("无法获取应用数据目录: {}", e))?;
+    if !app_data_dir.exists() {
+        std::fs::create_dir_all(&app_data_dir).map_err(|e| format!("创建应用数据目录失败: {}", e))?;
+    }
+
+    let staged_path = app_data_dir.join("pending_update");
+    std::fs::write(&staged_path, &bytes).map_err(|e| format!("保存安装包失败: {}", e))?;
+
+    emit_progress(app_handle, "app", "staging", "安装包已就绪，等待用户确认安装");
+    Ok(staged_path)
+}
+
+// 下载并替换Clash内核二进制，替换前后协调stop_clash/start_clash，确保内核文件没有被占用
+async fn update_clash_core(app_handle: &AppHandle<Wry>, manifest: &ReleaseManifest) -> Result<(), String> {
+    emit_progress(app_handle, "clash", "downloading", "正在下载新版Clash内核...");
+    let bytes = download_and_verify(&manifest.clash_download_url, &manifest.clash_sha256).await?;
+
+    emit_progress(app_handle, "clash", "verifying", "校验通过，准备替换内核...");
+    crate::commands::stop_clash(app_handle.clone()).map_err(|e| format!("停止Clash失败，无法替换内核: {}", e))?;
+
+    let bin_path = clash_bin_path(app_handle)?;
+    emit_progress(app_handle, "clash", "staging", "正在写入新版内核...");
+    std::fs::write(&bin_path, &bytes).map_err(|e| format!("写入新版Clash内核失败: {}", e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = std::fs::metadata(&bin_path) {
+            let mut permissions = metadata.permissions();
+            permissions.set_mode(0o755);
+            let _ = std::fs::set_permissions(&bin_path, permissions);
+        }
+    }
+
+    emit_progress(app_handle, "clash", "restarting", "正在重启Clash...");
+    crate::commands::start_clash(app_handle.clone()).map_err(|e| format!("重启Clash失败: {}", e))?;
+
+    println!("Clash内核已更新到{}", manifest.clash_version);
+    Ok(())
+}
+
+// 对外共用的检查流程，供命令和.setup中的自动检查共同调用
+pub async fn run_update_check(app_handle: &AppHandle<Wry>) -> Result<UpdateCheckResult, String> {
+    emit_progress(app_handle, "app", "checking", "正在检查更新...");
+    let manifest = fetch_manifest().await?;
+
+    let current_app_version = app_handle.package_info().version.to_string();
+    let mut app_update_staged_path = None;
+
+    if manifest.app_version != current_app_version {
+        match stage_app_update(app_handle, &manifest).await {
+            Ok(path) => app_update_staged_path = Some(path.to_string_lossy().to_string()),
+            Err(e) => {
+                eprintln!("应用更新失败: {}", e);
+                emit_progress(app_handle, "app", "failed", e);
+            }
+        }
+    } else {
+        emit_progress(app_handle, "app", "up_to_date", "应用已是最新版本");
+    }
+
+    let installed_clash_version = read_installed_clash_version(app_handle);
+    let mut clash_version_updated_to = None;
+
+    if installed_clash_version.as_deref() != Some(manifest.clash_version.as_str()) {
+        match update_clash_core(app_handle, &manifest).await {
+            Ok(()) => {
+                write_installed_clash_version(app_handle, &manifest.clash_version);
+                clash_version_updated_to = Some(manifest.clash_version.clone());
+            }
+            Err(e) => {
+                eprintln!("Clash内核更新失败: {}", e);
+                emit_progress(app_handle, "clash", "failed", e);
+            }
+        }
+    } else {
+        emit_progress(app_handle, "clash", "up_to_date", "Clash内核已是最新版本");
+    }
+
+    Ok(UpdateCheckResult {
+        app_version: current_app_version,
+        latest_app_version: manifest.app_version,
+        app_update_staged_path,
+        clash_version_updated_to,
+    })
+}
+
+/// 检查应用和Clash内核的更新，如有新版本则下载校验后替换（Clash内核）或暂存（应用安装包）
+#[tauri::command]
+pub async fn check_for_updates(app_handle: AppHandle<Wry>) -> Result<UpdateCheckResult, String> {
+    run_update_check(&app_handle).await
+}
@@ -145,7 +145,7 @@ async fn login_by_token(access_token: String, device_user_id: Option<String>, ap
     let account_data = api_response.data;
     
     // 使用公共方法更新账号信息
-    if let Err(e) = account_manager.update_account(account_data.clone(), Some(&app_handle)) {
+    if let Err(e) = account_manager.update_account(account_data.clone(), Some(&app_handle)).await {
         return Err(format!("更新账号信息失败: {}", e));
     }
     